@@ -1,5 +1,5 @@
 import React, { useState, useEffect, useRef } from 'react';
-import { Card, Form, Input, Button, Table, DatePicker, Space, InputNumber, Select, Typography, Tag } from 'antd';
+import { Card, Form, Input, Button, Table, DatePicker, Space, InputNumber, Select, Typography, Tag, Progress } from 'antd';
 import type { TableProps } from 'antd';
 import dayjs from 'dayjs';
 import weekday from 'dayjs/plugin/weekday';
@@ -40,7 +40,7 @@ const defaultValidExtensions = [
 
 const defaultIgnoredPaths = [
   'dist', 'node_modules/', 'build/',
-  '.husky', 'lintrc', 'public/',
+  '.husky', '*lintrc*', 'public/',
 ];
 
 interface CodeStat {
@@ -58,6 +58,23 @@ interface CodeStat {
   children?: CodeStat[];
 }
 
+// 分析进度事件，对应 on_progress 回调的参数
+interface ProgressEvent {
+  type: 'projectsDiscovered' | 'projectStarted' | 'commitsFetched' | 'diffsProcessed'
+    | 'projectFinished' | 'retry' | 'failure' | 'cancelled';
+  project?: string;
+  analyzed?: number;
+  finished?: number;
+  total?: number;
+  etaMs?: number;
+}
+
+interface ProgressState {
+  finished: number;
+  total: number;
+  etaMs?: number;
+}
+
 const GitLabAnalysis: React.FC = () => {
   const [form] = Form.useForm();
   const [loading, setLoading] = useState(false);
@@ -69,6 +86,8 @@ const GitLabAnalysis: React.FC = () => {
   const [expandedKeys, setExpandedKeys] = useState<string[]>([]);
   const [dateRange, setDateRange] = useState<[string, string]>(['', '']);
   const [showFireworks, setShowFireworks] = useState(false);
+  const [progress, setProgress] = useState<ProgressState>({ finished: 0, total: 0 });
+  const abortControllerRef = useRef<AbortController | null>(null);
 
   // 添加防抖的滚动函数
   const smoothScrollToBottom = useRef(
@@ -220,6 +239,8 @@ const GitLabAnalysis: React.FC = () => {
     try {
       setLoading(true);
       setLogs([]);
+      setProgress({ finished: 0, total: 0 });
+      abortControllerRef.current = new AbortController();
       console.log('开始分析...');
 
       const dateRange = values.date_range;
@@ -239,6 +260,16 @@ const GitLabAnalysis: React.FC = () => {
         valid_extensions: values.valid_extensions,
         ignored_paths: values.ignored_paths,
         max_concurrent_requests: values.concurrency,
+        // 取消分析时返回已完成部分的报告
+        signal: abortControllerRef.current.signal,
+        // 根据进度事件更新进度条
+        on_progress: (event: ProgressEvent) => {
+          if (event.type === 'projectsDiscovered') {
+            setProgress({ finished: 0, total: event.analyzed ?? 0 });
+          } else if (event.type === 'projectFinished') {
+            setProgress({ finished: event.finished ?? 0, total: event.total ?? 0, etaMs: event.etaMs });
+          }
+        },
       };
 
       // eslint-disable-next-line @typescript-eslint/naming-convention
@@ -270,7 +301,7 @@ const GitLabAnalysis: React.FC = () => {
       setExpandedKeys(allKeys);
       setCommitStats(result.commitStats || []);
       setFailureStats(result.failureStats || []);
-      console.log('分析完成！');
+      console.log(result.cancelled ? '分析已取消，展示已完成部分的结果' : '分析完成！');
       setShowFireworks(true);
     } catch (error) {
       console.error('分析失败:', error);
@@ -411,6 +442,16 @@ const GitLabAnalysis: React.FC = () => {
               >
                 开始分析
               </Button>
+
+              {loading && (
+                <Button
+                  danger
+                  block
+                  onClick={() => abortControllerRef.current?.abort()}
+                >
+                  取消分析
+                </Button>
+              )}
             </Space>
           </Form>
         </Card>
@@ -431,6 +472,16 @@ const GitLabAnalysis: React.FC = () => {
           </Button>
         }
       >
+        {progress.total > 0 && (
+          <Progress
+            percent={Math.round((progress.finished / progress.total) * 100)}
+            format={() => `${progress.finished}/${progress.total}${
+              progress.etaMs && progress.finished < progress.total
+                ? ` 预计剩余 ${Math.ceil(progress.etaMs / 1000)}s`
+                : ''
+            }`}
+          />
+        )}
         <div className={'logContainer'} ref={logContainerRef}>
           {logs.length === 0 ? (
             <div className={'emptyLog'}>暂无日志</div>
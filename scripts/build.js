@@ -9,10 +9,12 @@ const TARGETS = [
     target: 'web',
     outDir: 'pkg/web',
   },
-  // {
-  //   target: 'nodejs',
-  //   outDir: 'pkg/node',
-  // },
+  {
+    target: 'nodejs',
+    outDir: 'pkg/node',
+    // node 环境使用全局 fetch，不依赖 window
+    features: 'node',
+  },
   {
     target: 'bundler',
     outDir: 'pkg/bundler',
@@ -23,7 +25,7 @@ const TARGETS = [
   },
 ];
 
-async function buildTarget({ target, outDir }) {
+async function buildTarget({ target, outDir, features }) {
   console.log(`🚀 开始构建 target: ${target}...`);
 
   try {
@@ -31,7 +33,8 @@ async function buildTarget({ target, outDir }) {
     await mkdir(outDir, { recursive: true });
 
     // 执行wasm-pack构建
-    const cmd = `wasm-pack build --out-dir ${outDir} --target ${target} --scope gogors`;
+    const featureArgs = features ? ` -- --no-default-features --features ${features}` : '';
+    const cmd = `wasm-pack build --out-dir ${outDir} --target ${target} --scope gogors${featureArgs}`;
     const { stderr } = await execAsync(cmd);
 
     if (stderr.includes('error')) {
@@ -0,0 +1,110 @@
+// 机器人账号识别
+// 依赖升级、CI 发版等机器人提交的代码量通常很大（锁文件、生成文件），会挤到代码统计的前面
+// 以下任一条件满足时视为机器人：名字以 [bot] 结尾、名字或邮箱匹配 bot_patterns、匹配到的 GitLab 账号为机器人账号
+
+use crate::{AuthorStats, GitlabUser};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// 未配置 bot_patterns 时使用的规则，GitLab 项目/群组访问令牌的用户名形如 project_123_bot_xxx
+// 自建 GitLab 中由集成和脚本以默认管理员身份生成的提交，作者名为 Administrator
+pub const DEFAULT_BOT_PATTERNS: &[&str] = &[
+    "renovate",
+    "dependabot",
+    r"^(project|group)_?\d+_bot",
+    "^administrator$",
+];
+
+// 机器人提交的统计方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BotPolicy {
+    // 在 botStats 中单独统计
+    #[default]
+    Separate,
+    // 不统计
+    Exclude,
+}
+
+#[derive(Debug)]
+pub struct BotDetector {
+    patterns: Vec<Regex>,
+}
+
+impl BotDetector {
+    // 规则不区分大小写，无效的正则返回错误
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Result<Self, String> {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| {
+                RegexBuilder::new(pattern.as_ref())
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| format!("bot_patterns 中的 {} 无效: {}", pattern.as_ref(), e))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(BotDetector { patterns })
+    }
+
+    pub fn is_bot(&self, name: &str, email: &str, user: Option<&GitlabUser>) -> bool {
+        let local_part = email.split('@').next().unwrap_or("");
+        user.is_some_and(|user| user.bot)
+            || name.trim_end().to_lowercase().ends_with("[bot]")
+            || local_part.to_lowercase().ends_with("[bot]")
+            || self
+                .patterns
+                .iter()
+                .any(|pattern| pattern.is_match(name) || pattern.is_match(email))
+    }
+
+    // 把作者统计分为 (人, 机器人) 两部分
+    pub fn partition(
+        &self,
+        author_stats: HashMap<String, AuthorStats>,
+        users: Option<&HashMap<String, GitlabUser>>,
+    ) -> (HashMap<String, AuthorStats>, HashMap<String, AuthorStats>) {
+        author_stats.into_iter().partition(|(key, stat)| {
+            let user = users.and_then(|users| users.get(key));
+            !self.is_bot(&stat.author_name, &stat.author_email, user)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_patterns_match_common_bots() {
+        let bots = BotDetector::new(DEFAULT_BOT_PATTERNS).unwrap();
+        assert!(bots.is_bot("renovate[bot]", "bot@renovateapp.com", None));
+        assert!(bots.is_bot("dependabot", "support@github.com", None));
+        assert!(bots.is_bot("project_42_bot_1a2b", "project42_bot@noreply.gitlab.com", None));
+        assert!(bots.is_bot("Administrator", "admin@example.com", None));
+        assert!(bots.is_bot("Release", "release[bot]@example.com", None));
+        assert!(!bots.is_bot("Alice Administrator", "alice@example.com", None));
+        assert!(!bots.is_bot("Alice", "alice@example.com", None));
+    }
+
+    #[test]
+    fn gitlab_bot_accounts_are_bots() {
+        let bots = BotDetector::new::<&str>(&[]).unwrap();
+        let user = GitlabUser {
+            id: 1,
+            username: "ci".to_string(),
+            name: "CI".to_string(),
+            state: "active".to_string(),
+            avatar_url: None,
+            web_url: None,
+            bot: true,
+        };
+        assert!(bots.is_bot("CI", "ci@example.com", Some(&user)));
+        assert!(!bots.is_bot("CI", "ci@example.com", None));
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        assert!(BotDetector::new(&["("]).is_err());
+    }
+}
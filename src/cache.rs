@@ -0,0 +1,226 @@
+// 提交差异的响应缓存
+// 同一个提交的 diff 不会变化，缓存后重复分析重叠的时间范围时只需要请求新增提交的差异
+// 提交所属的分支和标签会随新建分支、打标签而变化，因此 refs 不缓存
+// 缓存 key 由实例地址、项目 id 和提交 sha 组成
+// 浏览器中使用 IndexedDB，其他环境可以通过 js 对象或实现 ResponseCache 接入自定义存储，测试中使用内存缓存
+
+use futures::future::LocalBoxFuture;
+use js_sys::{Function, Promise, Reflect};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{IdbDatabase, IdbFactory, IdbOpenDbRequest, IdbRequest, IdbTransactionMode};
+
+// IndexedDB 默认的数据库名
+pub const DEFAULT_DB_NAME: &str = "gitlab-analysis-cache";
+
+const DB_VERSION: u32 = 1;
+const STORE_NAME: &str = "responses";
+
+// 缓存的接口类型
+#[derive(Clone, Copy, Debug)]
+pub enum CacheKind {
+    Diff,
+}
+
+// 生成缓存 key，实例地址末尾的 / 不影响结果
+pub fn cache_key(kind: CacheKind, instance: &str, project_id: i64, sha: &str) -> String {
+    let kind = match kind {
+        CacheKind::Diff => "diff",
+    };
+    format!("{}|{}|{}|{}", instance.trim_end_matches('/'), project_id, sha, kind)
+}
+
+// === 缓存抽象 ===
+// 缓存的是原始响应体，读写失败只记录日志，不影响分析
+pub trait ResponseCache {
+    fn get<'a>(&'a self, key: &'a str) -> LocalBoxFuture<'a, Result<Option<String>, String>>;
+
+    fn put<'a>(&'a self, key: &'a str, body: &'a str) -> LocalBoxFuture<'a, Result<(), String>>;
+}
+
+// === 内存实现 ===
+#[derive(Default)]
+pub struct MemoryCache {
+    entries: RefCell<HashMap<String, String>>,
+}
+
+impl MemoryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.borrow().is_empty()
+    }
+}
+
+impl ResponseCache for MemoryCache {
+    fn get<'a>(&'a self, key: &'a str) -> LocalBoxFuture<'a, Result<Option<String>, String>> {
+        let value = self.entries.borrow().get(key).cloned();
+        Box::pin(futures::future::ready(Ok(value)))
+    }
+
+    fn put<'a>(&'a self, key: &'a str, body: &'a str) -> LocalBoxFuture<'a, Result<(), String>> {
+        self.entries.borrow_mut().insert(key.to_string(), body.to_string());
+        Box::pin(futures::future::ready(Ok(())))
+    }
+}
+
+// === IndexedDB 实现 ===
+// 浏览器主线程和 Web Worker 中均可使用，数据库在第一次读写时打开
+pub struct IndexedDbCache {
+    name: String,
+    db: RefCell<Option<IdbDatabase>>,
+}
+
+impl IndexedDbCache {
+    // 当前环境不支持 IndexedDB 时返回 None
+    pub fn new(name: impl Into<String>) -> Option<Self> {
+        factory()?;
+        Some(IndexedDbCache {
+            name: name.into(),
+            db: RefCell::new(None),
+        })
+    }
+
+    async fn database(&self) -> Result<IdbDatabase, JsValue> {
+        if let Some(db) = self.db.borrow().as_ref() {
+            return Ok(db.clone());
+        }
+
+        let factory = factory().ok_or_else(|| JsValue::from_str("当前环境不支持 IndexedDB"))?;
+        let request = factory.open_with_u32(&self.name, DB_VERSION)?;
+
+        // 首次打开时创建存储空间
+        let on_upgrade = Closure::<dyn FnMut(web_sys::Event)>::new(|event: web_sys::Event| {
+            let Some(request) = event.target() else { return };
+            let request: IdbOpenDbRequest = request.unchecked_into();
+            if let Ok(db) = request.result() {
+                let db: IdbDatabase = db.unchecked_into();
+                if !db.object_store_names().contains(STORE_NAME) {
+                    let _ = db.create_object_store(STORE_NAME);
+                }
+            }
+        });
+        request.set_onupgradeneeded(Some(on_upgrade.as_ref().unchecked_ref()));
+        let result = request_result(&request).await;
+        request.set_onupgradeneeded(None);
+
+        let db: IdbDatabase = result?.dyn_into()?;
+        *self.db.borrow_mut() = Some(db.clone());
+        Ok(db)
+    }
+
+    async fn get_value(&self, key: &str) -> Result<Option<String>, JsValue> {
+        let db = self.database().await?;
+        let store = db.transaction_with_str(STORE_NAME)?.object_store(STORE_NAME)?;
+        let request = store.get(&JsValue::from_str(key))?;
+        Ok(request_result(&request).await?.as_string())
+    }
+
+    async fn put_value(&self, key: &str, body: &str) -> Result<(), JsValue> {
+        let db = self.database().await?;
+        let store = db
+            .transaction_with_str_and_mode(STORE_NAME, IdbTransactionMode::Readwrite)?
+            .object_store(STORE_NAME)?;
+        let request = store.put_with_key(&JsValue::from_str(body), &JsValue::from_str(key))?;
+        request_result(&request).await?;
+        Ok(())
+    }
+}
+
+impl ResponseCache for IndexedDbCache {
+    fn get<'a>(&'a self, key: &'a str) -> LocalBoxFuture<'a, Result<Option<String>, String>> {
+        Box::pin(async move { self.get_value(key).await.map_err(|e| format!("{:?}", e)) })
+    }
+
+    fn put<'a>(&'a self, key: &'a str, body: &'a str) -> LocalBoxFuture<'a, Result<(), String>> {
+        Box::pin(async move { self.put_value(key, body).await.map_err(|e| format!("{:?}", e)) })
+    }
+}
+
+fn factory() -> Option<IdbFactory> {
+    Reflect::get(&js_sys::global(), &"indexedDB".into())
+        .ok()?
+        .dyn_into::<IdbFactory>()
+        .ok()
+}
+
+// 等待 IndexedDB 请求完成并返回结果
+async fn request_result(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let promise = Promise::new(&mut |resolve, reject| {
+        request.set_onsuccess(Some(&resolve));
+        request.set_onerror(Some(&reject));
+    });
+    let result = JsFuture::from(promise).await;
+    request.set_onsuccess(None);
+    request.set_onerror(None);
+
+    match result {
+        Ok(_) => request.result(),
+        Err(_) => Err(request
+            .error()
+            .ok()
+            .flatten()
+            .map(|e| JsValue::from_str(&e.message()))
+            .unwrap_or_else(|| JsValue::from_str("IndexedDB 请求失败"))),
+    }
+}
+
+// === js 对象实现 ===
+// 传入带有 get(key) 和 set(key, value) 方法的对象（例如 Node 中基于文件或 Redis 的存储），方法可以返回 Promise
+pub struct JsCache {
+    store: JsValue,
+    get: Function,
+    set: Function,
+}
+
+impl JsCache {
+    // 对象缺少 get 或 set 方法时返回 None
+    pub fn new(store: JsValue) -> Option<Self> {
+        let method = |name: &str| {
+            Reflect::get(&store, &name.into())
+                .ok()?
+                .dyn_into::<Function>()
+                .ok()
+        };
+        let get = method("get")?;
+        let set = method("set")?;
+        Some(JsCache { store, get, set })
+    }
+}
+
+impl ResponseCache for JsCache {
+    fn get<'a>(&'a self, key: &'a str) -> LocalBoxFuture<'a, Result<Option<String>, String>> {
+        Box::pin(async move {
+            let value = resolve(self.get.call1(&self.store, &JsValue::from_str(key)))
+                .await
+                .map_err(|e| format!("{:?}", e))?;
+            Ok(value.as_string())
+        })
+    }
+
+    fn put<'a>(&'a self, key: &'a str, body: &'a str) -> LocalBoxFuture<'a, Result<(), String>> {
+        Box::pin(async move {
+            resolve(self.set.call2(&self.store, &JsValue::from_str(key), &JsValue::from_str(body)))
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("{:?}", e))
+        })
+    }
+}
+
+// 方法返回 Promise 时等待其完成
+async fn resolve(value: Result<JsValue, JsValue>) -> Result<JsValue, JsValue> {
+    let value = value?;
+    match value.dyn_into::<Promise>() {
+        Ok(promise) => JsFuture::from(promise).await,
+        Err(value) => Ok(value),
+    }
+}
@@ -0,0 +1,40 @@
+// 取消分析
+// js 中通过 Config 传入 AbortSignal，signal 触发后中断所有进行中的请求，并停止调度新的项目和提交
+// 非 js 环境中可以直接调用 cancel()
+
+use std::cell::Cell;
+use std::rc::Rc;
+use web_sys::AbortSignal;
+
+#[derive(Clone, Default, Debug)]
+pub struct CancelToken {
+    cancelled: Rc<Cell<bool>>,
+    signal: Option<AbortSignal>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 绑定外部的 AbortSignal
+    pub fn from_signal(signal: AbortSignal) -> Self {
+        CancelToken {
+            cancelled: Rc::new(Cell::new(false)),
+            signal: Some(signal),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.set(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.get() || self.signal.as_ref().is_some_and(|signal| signal.aborted())
+    }
+
+    // 外部的 AbortSignal，js 传输层用它中断进行中的 fetch
+    pub fn signal(&self) -> Option<&AbortSignal> {
+        self.signal.as_ref()
+    }
+}
@@ -0,0 +1,261 @@
+// 断点续跑
+// 分析过程中定期通过 on_checkpoint 回调导出断点，包含已完成的项目、已处理的提交和累计的作者统计
+// 下次分析时通过 Config.resume_from 传入断点，跳过已经处理过的项目和提交
+
+use crate::{AuthorStats, Config};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Mutex;
+
+// 断点格式版本，格式不兼容时递增
+pub const CHECKPOINT_VERSION: u32 = 4;
+
+// 默认每处理多少个提交导出一次断点
+pub const DEFAULT_CHECKPOINT_INTERVAL: u32 = 50;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Checkpoint {
+    version: u32,
+    // 断点对应的分析范围，与配置不一致时不能用于恢复
+    scope: CheckpointScope,
+    // 已完成的项目 id
+    processed_projects: BTreeSet<i64>,
+    // 已处理的提交，key 为项目 id
+    processed_commits: BTreeMap<String, BTreeSet<String>>,
+    // 累计的作者统计
+    author_stats: HashMap<String, AuthorStats>,
+    // 不在作者统计中的合并提交数：merge_policy 为 exclude 或作者都是被排除的机器人
+    excluded_merges: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct CheckpointScope {
+    gitlab_api: String,
+    group_id: String,
+    start_date: String,
+    end_date: String,
+    // 影响统计结果的配置，恢复时必须一致，否则断点中的统计与新统计的口径不同
+    #[serde(default)]
+    options: BTreeMap<String, Value>,
+}
+
+impl CheckpointScope {
+    fn from_config(config: &Config) -> Self {
+        let options = [
+            ("projects_num", json!(config.projects_num)),
+            ("excluded_projects", json!(config.excluded_projects)),
+            ("valid_extensions", json!(config.valid_extensions)),
+            ("ignored_paths", json!(config.ignored_paths)),
+            ("fast_mode", json!(config.fast_mode)),
+            ("mailmap", json!(config.mailmap)),
+            ("fetch_mailmap", json!(config.fetch_mailmap)),
+            ("dedup_policy", json!(config.dedup_policy)),
+            ("merge_policy", json!(config.merge_policy)),
+            ("revert_policy", json!(config.revert_policy)),
+            ("coauthor_policy", json!(config.coauthor_policy)),
+            ("bot_patterns", json!(config.bot_patterns)),
+            ("bot_policy", json!(config.bot_policy)),
+            ("generated_detection", json!(config.generated_detection)),
+            ("fetch_gitattributes", json!(config.fetch_gitattributes)),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect();
+
+        CheckpointScope {
+            gitlab_api: config.gitlab_api.clone(),
+            group_id: config.group_id.clone(),
+            start_date: config.start_date.clone(),
+            end_date: config.end_date.clone(),
+            options,
+        }
+    }
+}
+
+impl Checkpoint {
+    // 校验断点是否可以用于当前配置
+    pub fn validate(&self, config: &Config) -> Result<(), String> {
+        if self.version != CHECKPOINT_VERSION {
+            return Err(format!(
+                "断点版本 {} 与当前版本 {} 不兼容",
+                self.version, CHECKPOINT_VERSION
+            ));
+        }
+        let scope = CheckpointScope::from_config(config);
+        if (&self.scope.gitlab_api, &self.scope.group_id, &self.scope.start_date, &self.scope.end_date)
+            != (&scope.gitlab_api, &scope.group_id, &scope.start_date, &scope.end_date)
+        {
+            return Err("断点的 gitlab_api、group_id 或时间范围与当前配置不一致".to_string());
+        }
+        let changed: Vec<_> = scope
+            .options
+            .iter()
+            .filter(|(name, value)| self.scope.options.get(*name) != Some(value))
+            .map(|(name, _)| name.as_str())
+            .collect();
+        if !changed.is_empty() {
+            return Err(format!("断点的 {} 与当前配置不一致", changed.join("、")));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn into_author_stats(self) -> HashMap<String, AuthorStats> {
+        self.author_stats
+    }
+}
+
+// 断点回调
+pub type CheckpointCallback = Box<dyn Fn(&Checkpoint)>;
+
+// 记录分析过程中已处理的部分
+#[derive(Default)]
+pub(crate) struct CheckpointState {
+    processed_projects: Mutex<BTreeSet<i64>>,
+    processed_commits: Mutex<BTreeMap<String, BTreeSet<String>>>,
+    excluded_merges: Mutex<u32>,
+    // 上次导出断点后新处理的提交数
+    pending: Mutex<u32>,
+}
+
+impl CheckpointState {
+    pub fn from_checkpoint(checkpoint: &Checkpoint) -> Self {
+        CheckpointState {
+            processed_projects: Mutex::new(checkpoint.processed_projects.clone()),
+            processed_commits: Mutex::new(checkpoint.processed_commits.clone()),
+            excluded_merges: Mutex::new(checkpoint.excluded_merges),
+            pending: Mutex::new(0),
+        }
+    }
+
+    pub fn is_project_processed(&self, project_id: i64) -> bool {
+        self.processed_projects.lock().unwrap().contains(&project_id)
+    }
+
+    pub fn is_commit_processed(&self, project_id: i64, sha: &str) -> bool {
+        self.processed_commits
+            .lock()
+            .unwrap()
+            .get(&project_id.to_string())
+            .is_some_and(|shas| shas.contains(sha))
+    }
+
+    // 已处理的提交及其所属项目
+    pub fn processed_shas(&self) -> HashMap<String, i64> {
+        let mut shas = HashMap::new();
+        for (project_id, commits) in self.processed_commits.lock().unwrap().iter() {
+            let Ok(project_id) = project_id.parse::<i64>() else {
+                continue;
+            };
+            for sha in commits {
+                shas.insert(sha.clone(), project_id);
+            }
+        }
+        shas
+    }
+
+    pub fn mark_project(&self, project_id: i64) {
+        self.processed_projects.lock().unwrap().insert(project_id);
+    }
+
+    // 记录已处理的提交，返回距离上次导出断点后新处理的提交数
+    pub fn mark_commit(&self, project_id: i64, sha: &str) -> u32 {
+        self.processed_commits
+            .lock()
+            .unwrap()
+            .entry(project_id.to_string())
+            .or_default()
+            .insert(sha.to_string());
+        let mut pending = self.pending.lock().unwrap();
+        *pending += 1;
+        *pending
+    }
+
+    // 记录跳过的合并提交，同时标记为已处理，恢复后不会重复计数
+    pub fn exclude_merge(&self, project_id: i64, sha: &str) -> u32 {
+        *self.excluded_merges.lock().unwrap() += 1;
+        self.mark_commit(project_id, sha)
+    }
+
+    pub fn excluded_merges(&self) -> u32 {
+        *self.excluded_merges.lock().unwrap()
+    }
+
+    // 生成断点快照
+    pub(crate) fn snapshot(&self, config: &Config, author_stats: &HashMap<String, AuthorStats>) -> Checkpoint {
+        *self.pending.lock().unwrap() = 0;
+        Checkpoint {
+            version: CHECKPOINT_VERSION,
+            scope: CheckpointScope::from_config(config),
+            processed_projects: self.processed_projects.lock().unwrap().clone(),
+            processed_commits: self.processed_commits.lock().unwrap().clone(),
+            author_stats: author_stats.clone(),
+            excluded_merges: *self.excluded_merges.lock().unwrap(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(extra: Value) -> Config {
+        let mut value = json!({
+            "gitlab_api": "http://gitlab.test/api/v4",
+            "gitlab_token": "token",
+            "group_id": "1",
+            "start_date": "2024-01-01",
+            "end_date": "2024-02-01",
+            "projects_num": 10,
+            "excluded_projects": [],
+            "valid_extensions": [".ts"],
+            "max_concurrent_requests": 3,
+            "ignored_paths": [],
+        });
+        if let (Some(value), Value::Object(extra)) = (value.as_object_mut(), extra) {
+            value.extend(extra);
+        }
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn checkpoint(config: &Config) -> Checkpoint {
+        CheckpointState::default().snapshot(config, &HashMap::new())
+    }
+
+    #[test]
+    fn accepts_options_that_do_not_affect_stats() {
+        let checkpoint = checkpoint(&config(json!({})));
+        let resumed = config(json!({
+            "gitlab_token": "other",
+            "max_concurrent_requests": 10,
+            "checkpoint_interval": 5,
+            "resolve_users": true,
+        }));
+        assert!(checkpoint.validate(&resumed).is_ok());
+    }
+
+    #[test]
+    fn rejects_changed_scope() {
+        let checkpoint = checkpoint(&config(json!({})));
+        assert!(checkpoint.validate(&config(json!({ "end_date": "2024-03-01" }))).is_err());
+    }
+
+    #[test]
+    fn rejects_changed_stat_options() {
+        let checkpoint = checkpoint(&config(json!({})));
+        let error = checkpoint
+            .validate(&config(json!({ "valid_extensions": [".js"], "merge_policy": "exclude" })))
+            .unwrap_err();
+        assert!(error.contains("valid_extensions") && error.contains("merge_policy"), "{}", error);
+        assert!(checkpoint
+            .validate(&config(json!({ "generated_detection": { "lockfile": false } })))
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut checkpoint = checkpoint(&config(json!({})));
+        checkpoint.version = CHECKPOINT_VERSION - 1;
+        assert!(checkpoint.validate(&config(json!({}))).is_err());
+    }
+}
@@ -0,0 +1,94 @@
+// 共同作者
+// 结对编程等场景下，提交信息末尾的 "Co-authored-by: Name <email>" 标注了其他作者，
+// 按 CoauthorPolicy 把提交的代码行分配给主作者和共同作者
+// 提交详情、文件统计等提交层面的数据始终只记在主作者名下，项目汇总不会因共同作者重复计算
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+static CO_AUTHORED_BY_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?mi)^[ \t]*Co-authored-by:[ \t]*([^<\r\n]*)<([^>\r\n]*)>").unwrap());
+
+// 共同作者的统计方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CoauthorPolicy {
+    // 每个作者都计入完整的提交和代码行
+    FullCredit,
+    // 每个作者都计入提交数，代码行平均分配
+    EvenSplit,
+    // 只统计提交的作者
+    #[default]
+    PrimaryOnly,
+}
+
+// 提交信息中的共同作者
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coauthor {
+    pub name: String,
+    pub email: String,
+}
+
+// 解析提交信息中的 Co-authored-by 标注
+pub fn parse(message: &str) -> Vec<Coauthor> {
+    CO_AUTHORED_BY_RE
+        .captures_iter(message)
+        .map(|captures| Coauthor {
+            name: captures[1].trim().to_string(),
+            email: captures[2].trim().to_string(),
+        })
+        .filter(|coauthor| !coauthor.name.is_empty() || !coauthor.email.is_empty())
+        .collect()
+}
+
+// 把 value 平均分成 parts 份后第 index 份的值，余数分给靠前的份，保证各份之和等于 value
+pub fn share(value: u64, parts: usize, index: usize) -> u64 {
+    let parts = parts.max(1) as u64;
+    let index = index as u64;
+    value / parts + u64::from(index < value % parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_co_authored_by_trailers() {
+        let message = "feat: pair\n\nCo-authored-by: Bob <bob@example.com>\nco-authored-by:Carol Wu<carol@example.com>\r\nSigned-off-by: Alice <alice@example.com>\nCo-authored-by: <>";
+        assert_eq!(
+            parse(message),
+            vec![
+                Coauthor {
+                    name: "Bob".to_string(),
+                    email: "bob@example.com".to_string(),
+                },
+                Coauthor {
+                    name: "Carol Wu".to_string(),
+                    email: "carol@example.com".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_trailers_inside_lines() {
+        assert!(parse("docs: explain Co-authored-by: Bob <bob@example.com>").is_empty());
+    }
+
+    #[test]
+    fn shares_sum_to_the_total() {
+        assert_eq!((0..3).map(|index| share(10, 3, index)).collect::<Vec<_>>(), vec![4, 3, 3]);
+        assert_eq!((0..4).map(|index| share(2, 4, index)).collect::<Vec<_>>(), vec![1, 1, 0, 0]);
+        for value in [0, 1, 7, 100] {
+            for parts in 1..6 {
+                assert_eq!((0..parts).map(|index| share(value, parts, index)).sum::<u64>(), value);
+            }
+        }
+    }
+
+    #[test]
+    fn zero_parts_keeps_the_whole_value() {
+        assert_eq!(share(5, 0, 0), 5);
+    }
+}
@@ -0,0 +1,215 @@
+// Conventional Commits 解析
+// 提交信息首行为 "<type>(<scope>)!: <description>" 格式，scope 和 ! 可省略，
+// 首行带 ! 或者正文中有 "BREAKING CHANGE:" / "BREAKING-CHANGE:" 脚注时视为破坏性变更
+// 报告中按作者和项目统计各类型的提交数，不符合格式的提交单独计数
+
+use crate::AuthorStats;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+static HEADER_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^([A-Za-z]+)(?:\(([^()]*)\))?(!)?: (\S.*)$").unwrap());
+static BREAKING_FOOTER_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^BREAKING[ -]CHANGE: ").unwrap());
+
+// 解析后的提交信息
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConventionalCommit {
+    // 小写的提交类型，例如 feat、fix
+    #[serde(rename = "type")]
+    pub commit_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    pub breaking: bool,
+    pub description: String,
+}
+
+// 解析提交信息，不符合格式时返回 None
+pub fn parse(message: &str) -> Option<ConventionalCommit> {
+    let header = message.lines().next()?.trim_end();
+    let captures = HEADER_RE.captures(header)?;
+    let scope = captures
+        .get(2)
+        .map(|m| m.as_str().trim())
+        .filter(|scope| !scope.is_empty())
+        .map(str::to_string);
+
+    Some(ConventionalCommit {
+        commit_type: captures[1].to_lowercase(),
+        scope,
+        breaking: captures.get(3).is_some() || BREAKING_FOOTER_RE.is_match(message),
+        description: captures[4].trim().to_string(),
+    })
+}
+
+// === 报告相关类型 ===
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitTypeReport {
+    by_project: Vec<ProjectCommitTypes>,
+    by_author: Vec<AuthorCommitTypes>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProjectCommitTypes {
+    project: String,
+    #[serde(flatten)]
+    counts: CommitTypeCounts,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AuthorCommitTypes {
+    author: String,
+    email: String,
+    #[serde(flatten)]
+    counts: CommitTypeCounts,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitTypeCounts {
+    commits: u32,
+    // 各类型的提交数
+    types: BTreeMap<String, u32>,
+    // 破坏性变更数，已计入对应类型
+    breaking: u32,
+    // 不符合格式的提交数
+    non_conforming: u32,
+}
+
+impl CommitTypeCounts {
+    fn record(&mut self, commit: Option<&ConventionalCommit>) {
+        self.commits += 1;
+        match commit {
+            Some(commit) => {
+                *self.types.entry(commit.commit_type.clone()).or_default() += 1;
+                if commit.breaking {
+                    self.breaking += 1;
+                }
+            }
+            None => self.non_conforming += 1,
+        }
+    }
+}
+
+// 按作者和项目统计提交类型
+pub fn build_report(author_stats: &HashMap<String, AuthorStats>) -> CommitTypeReport {
+    let mut projects: HashMap<&str, CommitTypeCounts> = HashMap::new();
+    let mut by_author = Vec::new();
+
+    for author_stat in author_stats.values() {
+        let mut counts = CommitTypeCounts::default();
+        for detail in &author_stat.commit_details {
+            let commit = parse(&detail.message);
+            counts.record(commit.as_ref());
+            projects.entry(&detail.project).or_default().record(commit.as_ref());
+        }
+        by_author.push(AuthorCommitTypes {
+            author: author_stat.author_name.clone(),
+            email: author_stat.author_email.clone(),
+            counts,
+        });
+    }
+
+    let mut by_project: Vec<_> = projects
+        .into_iter()
+        .map(|(project, counts)| ProjectCommitTypes {
+            project: project.to_string(),
+            counts,
+        })
+        .collect();
+
+    by_project.sort_by_key(|p| std::cmp::Reverse(p.counts.commits));
+    by_author.sort_by_key(|a| std::cmp::Reverse(a.counts.commits));
+
+    CommitTypeReport { by_project, by_author }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CommitDetail;
+
+    fn conventional(commit_type: &str, scope: Option<&str>, breaking: bool, description: &str) -> ConventionalCommit {
+        ConventionalCommit {
+            commit_type: commit_type.to_string(),
+            scope: scope.map(str::to_string),
+            breaking,
+            description: description.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_type_scope_and_description() {
+        assert_eq!(parse("feat(auth): add login"), Some(conventional("feat", Some("auth"), false, "add login")));
+        assert_eq!(parse("fix: typo\n\nbody"), Some(conventional("fix", None, false, "typo")));
+        assert_eq!(parse("chore(): bump"), Some(conventional("chore", None, false, "bump")));
+    }
+
+    #[test]
+    fn type_is_lowercased() {
+        assert_eq!(parse("Feat: add"), Some(conventional("feat", None, false, "add")));
+        assert_eq!(parse("FIX(Api): crash"), Some(conventional("fix", Some("Api"), false, "crash")));
+    }
+
+    #[test]
+    fn detects_breaking_changes() {
+        assert!(parse("feat(api)!: drop v1").unwrap().breaking);
+        assert!(parse("refactor!: rename").unwrap().breaking);
+        assert!(parse("feat: new config\n\nBREAKING CHANGE: config moved").unwrap().breaking);
+        assert!(parse("feat: new config\n\nBREAKING-CHANGE: config moved").unwrap().breaking);
+        // 脚注需要在行首
+        assert!(!parse("feat: mention BREAKING CHANGE: in text").unwrap().breaking);
+    }
+
+    #[test]
+    fn rejects_non_conforming_messages() {
+        assert_eq!(parse("Merge branch 'feature' into 'main'"), None);
+        assert_eq!(parse("Update README.md"), None);
+        assert_eq!(parse("feat:missing space"), None);
+        assert_eq!(parse("feat: "), None);
+        assert_eq!(parse(""), None);
+    }
+
+    #[test]
+    fn builds_report_by_project_and_author() {
+        let detail = |project: &str, message: &str| CommitDetail {
+            project: project.to_string(),
+            branch: "main".to_string(),
+            tag: String::new(),
+            message: message.to_string(),
+            committed_date: "2024-01-10T10:00:00Z".to_string(),
+        };
+        let mut alice = AuthorStats {
+            author_name: "Alice".to_string(),
+            author_email: "alice@example.com".to_string(),
+            ..Default::default()
+        };
+        alice.commit_details = vec![
+            detail("web", "feat: a"),
+            detail("web", "Feat!: b"),
+            detail("api", "Merge branch 'feature' into 'main'"),
+        ];
+        let mut bob = AuthorStats {
+            author_name: "Bob".to_string(),
+            author_email: "bob@example.com".to_string(),
+            ..Default::default()
+        };
+        bob.commit_details = vec![detail("web", "fix: c")];
+        let author_stats = HashMap::from([("alice".to_string(), alice), ("bob".to_string(), bob)]);
+
+        let report = serde_json::to_value(build_report(&author_stats)).unwrap();
+        assert_eq!(
+            report["byProject"],
+            serde_json::json!([
+                { "project": "web", "commits": 3, "types": { "feat": 2, "fix": 1 }, "breaking": 1, "nonConforming": 0 },
+                { "project": "api", "commits": 1, "types": {}, "breaking": 0, "nonConforming": 1 },
+            ])
+        );
+        assert_eq!(report["byAuthor"][0]["email"], "alice@example.com");
+        assert_eq!(report["byAuthor"][0]["types"], serde_json::json!({ "feat": 2 }));
+        assert_eq!(report["byAuthor"][0]["nonConforming"], 1);
+        assert_eq!(report["byAuthor"][1]["commits"], 1);
+    }
+}
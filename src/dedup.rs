@@ -0,0 +1,131 @@
+// 跨项目的提交去重
+// fork 与上游项目、镜像项目中会出现相同 sha 的提交，按归属策略只在一个项目中统计
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+// 重复提交的归属策略
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DedupPolicy {
+    // 归属于项目列表中最靠前的项目
+    FirstProject,
+    // 优先归属于上游项目，不是 fork 关系时按 first_project 处理
+    #[default]
+    UpstreamPreferred,
+    // 不去重，每个项目都统计
+    CountEverywhere,
+}
+
+// 参与去重的项目，按项目列表的顺序传入
+pub struct DedupProject {
+    pub project_id: i64,
+    // fork 的来源项目
+    pub forked_from: Option<i64>,
+}
+
+// 重复的提交归属于包含它且优先级最高的项目，返回每个项目需要先拿到哪些项目（下标）的提交列表才能确定归属
+// 优先级只取决于项目列表和 fork 关系，每个项目拿到这些列表后即可开始处理，不需要等待所有项目
+// 不去重时返回 None
+pub fn preferred_projects(projects: &[DedupProject], policy: DedupPolicy) -> Option<Vec<Vec<usize>>> {
+    let forked_from: HashMap<i64, i64> = projects
+        .iter()
+        .filter_map(|p| p.forked_from.map(|from| (p.project_id, from)))
+        .collect();
+    let in_scope: HashSet<i64> = projects.iter().map(|p| p.project_id).collect();
+
+    // 在本次分析范围内的 fork 层级，上游项目为 0
+    let fork_depth = |project_id: i64| {
+        let mut depth = 0;
+        let mut current = project_id;
+        while let Some(&from) = forked_from.get(&current) {
+            // 上游不在本次分析范围内，或者出现环时停止
+            if !in_scope.contains(&from) || depth > projects.len() {
+                break;
+            }
+            depth += 1;
+            current = from;
+        }
+        depth
+    };
+
+    // 层级相同时按项目列表的顺序
+    let ranks: Vec<(usize, usize)> = match policy {
+        DedupPolicy::CountEverywhere => return None,
+        DedupPolicy::FirstProject => (0..projects.len()).map(|index| (0, index)).collect(),
+        DedupPolicy::UpstreamPreferred => projects
+            .iter()
+            .enumerate()
+            .map(|(index, p)| (fork_depth(p.project_id), index))
+            .collect(),
+    };
+
+    Some(
+        ranks
+            .iter()
+            .map(|rank| (0..ranks.len()).filter(|&other| ranks[other] < *rank).collect())
+            .collect(),
+    )
+}
+
+// 提交是否在该项目中统计
+// claimed 为断点中已经统计过的提交，保持原来的归属，避免恢复后重复统计；preferred 为优先级更高的项目中的提交
+pub fn is_owner(sha: &str, project_id: i64, claimed: &HashMap<String, i64>, preferred: &HashSet<&str>) -> bool {
+    match claimed.get(sha) {
+        Some(&owner) => owner == project_id,
+        None => !preferred.contains(sha),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(project_id: i64, forked_from: Option<i64>) -> DedupProject {
+        DedupProject {
+            project_id,
+            forked_from,
+        }
+    }
+
+    #[test]
+    fn count_everywhere_does_not_wait() {
+        let projects = [project(1, None), project(2, Some(1))];
+        assert_eq!(preferred_projects(&projects, DedupPolicy::CountEverywhere), None);
+    }
+
+    #[test]
+    fn first_project_prefers_earlier_projects() {
+        let projects = [project(1, None), project(2, None), project(3, None)];
+        assert_eq!(
+            preferred_projects(&projects, DedupPolicy::FirstProject),
+            Some(vec![vec![], vec![0], vec![0, 1]])
+        );
+    }
+
+    #[test]
+    fn upstream_preferred_ranks_by_fork_depth() {
+        // 3 fork 自 2，2 fork 自 1，4 的上游不在范围内
+        let projects = [project(3, Some(2)), project(4, Some(99)), project(2, Some(1)), project(1, None)];
+        assert_eq!(
+            preferred_projects(&projects, DedupPolicy::UpstreamPreferred),
+            Some(vec![vec![1, 2, 3], vec![], vec![1, 3], vec![1]])
+        );
+    }
+
+    #[test]
+    fn fork_cycles_terminate() {
+        let projects = [project(1, Some(2)), project(2, Some(1))];
+        assert!(preferred_projects(&projects, DedupPolicy::UpstreamPreferred).is_some());
+    }
+
+    #[test]
+    fn claimed_commits_keep_their_owner() {
+        let claimed = HashMap::from([("a".to_string(), 2)]);
+        let preferred = HashSet::from(["a", "b"]);
+        assert!(is_owner("a", 2, &claimed, &preferred));
+        assert!(!is_owner("a", 1, &claimed, &HashSet::new()));
+        assert!(!is_owner("b", 2, &claimed, &preferred));
+        assert!(is_owner("c", 2, &claimed, &preferred));
+    }
+}
@@ -0,0 +1,153 @@
+// unified diff 解析
+// 按 @@ 头中的行数逐行归类，hunk 内以 + 开头的行都是新增行（包括 ++i、+++ 这类内容），不会被误判为文件头
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static HUNK_HEADER_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^@@ -\d+(?:,(\d+))? \+\d+(?:,(\d+))? @@").unwrap());
+
+// 单个文件的差异统计
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DiffStats {
+    pub additions: u32,
+    pub deletions: u32,
+    pub hunks: u32,
+}
+
+// hunk 中各行的类型
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Line<'a> {
+    HunkHeader,
+    // 去掉开头 + 号后的内容
+    Added(&'a str),
+    Deleted(&'a str),
+    Context,
+}
+
+// 逐行归类，hunk 之外的行（diff --git、index、---/+++ 文件头、Binary files 等）和 "\ No newline" 标记不返回
+pub fn lines(diff: &str) -> impl Iterator<Item = Line<'_>> {
+    // 当前 hunk 中剩余的旧文件行数和新文件行数
+    let mut old_remaining = 0u32;
+    let mut new_remaining = 0u32;
+
+    diff.lines().filter_map(move |line| {
+        if old_remaining == 0 && new_remaining == 0 {
+            let captures = HUNK_HEADER_RE.captures(line)?;
+            // 省略行数时默认为 1
+            let count = |index| {
+                captures
+                    .get(index)
+                    .map_or(1, |m| m.as_str().parse().unwrap_or(0))
+            };
+            old_remaining = count(1);
+            new_remaining = count(2);
+            return Some(Line::HunkHeader);
+        }
+
+        match line.as_bytes().first() {
+            Some(b'+') => {
+                new_remaining = new_remaining.saturating_sub(1);
+                Some(Line::Added(&line[1..]))
+            }
+            Some(b'-') => {
+                old_remaining = old_remaining.saturating_sub(1);
+                Some(Line::Deleted(&line[1..]))
+            }
+            // "\ No newline at end of file"
+            Some(b'\\') => None,
+            // 上下文行，部分工具会去掉空行前面的空格
+            _ => {
+                old_remaining = old_remaining.saturating_sub(1);
+                new_remaining = new_remaining.saturating_sub(1);
+                Some(Line::Context)
+            }
+        }
+    })
+}
+
+// 新增行的内容
+pub fn added_lines(diff: &str) -> impl Iterator<Item = &str> {
+    lines(diff).filter_map(|line| match line {
+        Line::Added(content) => Some(content),
+        _ => None,
+    })
+}
+
+// 解析单个文件的 diff 内容
+pub fn parse(diff: &str) -> DiffStats {
+    let mut stats = DiffStats::default();
+    for line in lines(diff) {
+        match line {
+            Line::HunkHeader => stats.hunks += 1,
+            Line::Added(_) => stats.additions += 1,
+            Line::Deleted(_) => stats.deletions += 1,
+            Line::Context => {}
+        }
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(additions: u32, deletions: u32, hunks: u32) -> DiffStats {
+        DiffStats {
+            additions,
+            deletions,
+            hunks,
+        }
+    }
+
+    #[test]
+    fn counts_content_lines_that_look_like_headers() {
+        let diff = "@@ -1,3 +1,3 @@\n-i--;\n---flag\n----- old\n+++i;\n+++ new\n+--flag\n";
+        assert_eq!(parse(diff), stats(3, 3, 1));
+    }
+
+    #[test]
+    fn skips_file_headers_before_the_first_hunk() {
+        let diff = "diff --git a/a.c b/a.c\nindex 1..2 100644\n--- a/a.c\n+++ b/a.c\n@@ -1,2 +1,2 @@\n int a;\n-int b;\n+int c;\n";
+        assert_eq!(parse(diff), stats(1, 1, 1));
+    }
+
+    #[test]
+    fn counts_multiple_hunks() {
+        let diff = "@@ -1,2 +1,3 @@\n a\n+b\n c\n@@ -10,3 +11,2 @@\n x\n-y\n z\n@@ -20 +20,2 @@\n-old\n+new\n+more\n";
+        assert_eq!(parse(diff), stats(3, 2, 3));
+    }
+
+    #[test]
+    fn omitted_count_defaults_to_one() {
+        assert_eq!(parse("@@ -1 +1 @@\n-a\n+b\n"), stats(1, 1, 1));
+        // 省略行数的 hunk 结束后，下一行 @@ 是新的 hunk 头
+        assert_eq!(parse("@@ -1 +1 @@\n-a\n+b\n@@ -5 +5 @@\n-c\n+d\n"), stats(2, 2, 2));
+    }
+
+    #[test]
+    fn ignores_no_newline_marker() {
+        let diff = "@@ -1,1 +1,1 @@\n-a\n\\ No newline at end of file\n+a\n\\ No newline at end of file\n";
+        assert_eq!(parse(diff), stats(1, 1, 1));
+    }
+
+    #[test]
+    fn context_lines_without_leading_space() {
+        // 空的上下文行被去掉了前导空格
+        let diff = "@@ -1,3 +1,3 @@\n a\n\n-b\n+c\n";
+        assert_eq!(parse(diff), stats(1, 1, 1));
+    }
+
+    #[test]
+    fn ignores_binary_and_new_empty_files() {
+        assert_eq!(parse("Binary files a/x.png and b/x.png differ\n"), stats(0, 0, 0));
+        assert_eq!(parse(""), stats(0, 0, 0));
+        assert_eq!(parse("@@ -0,0 +1,2 @@\n+a\n+b\n"), stats(2, 0, 1));
+    }
+
+    #[test]
+    fn yields_added_line_content() {
+        let diff = "--- a/x.js\n+++ b/x.js\n@@ -1,2 +1,3 @@\n keep\n-old\n+++i;\n+new\n\\ No newline at end of file\n";
+        assert_eq!(added_lines(diff).collect::<Vec<_>>(), vec!["++i;", "new"]);
+    }
+}
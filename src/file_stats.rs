@@ -0,0 +1,188 @@
+// 文件级别的统计
+// 保留每个文件的增删行数和变更类型，报告中按项目和作者分别汇总，可以看出作者改动集中在哪些文件
+
+use crate::AuthorStats;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// 文件变更类型，统计周期内同一文件有多种变更时按 added > deleted > renamed > modified 取优先级最高的
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    #[default]
+    Modified,
+    Renamed,
+    Deleted,
+    Added,
+}
+
+impl FileStatus {
+    // 根据 GitLab diff 接口中的 new_file、renamed_file、deleted_file 判断
+    pub fn from_flags(new_file: bool, renamed_file: bool, deleted_file: bool) -> Self {
+        if new_file {
+            FileStatus::Added
+        } else if deleted_file {
+            FileStatus::Deleted
+        } else if renamed_file {
+            FileStatus::Renamed
+        } else {
+            FileStatus::Modified
+        }
+    }
+}
+
+// 单个提交中一个文件的变更
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FileChange {
+    pub path: String,
+    // 重命名前的路径
+    pub old_path: Option<String>,
+    pub status: FileStatus,
+    pub additions: u32,
+    pub deletions: u32,
+}
+
+// 一个文件在统计周期内的累计变更
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct FileStat {
+    old_path: Option<String>,
+    status: FileStatus,
+    commits: u32,
+    additions: u32,
+    deletions: u32,
+}
+
+impl FileStat {
+    pub fn record(&mut self, change: &FileChange) {
+        if change.old_path.is_some() {
+            self.old_path = change.old_path.clone();
+        }
+        self.status = self.status.max(change.status);
+        self.commits += 1;
+        self.additions += change.additions;
+        self.deletions += change.deletions;
+    }
+
+    // 变更行数
+    pub fn lines(&self) -> u32 {
+        self.additions + self.deletions
+    }
+
+    fn merge(&mut self, other: &FileStat) {
+        if other.old_path.is_some() {
+            self.old_path = other.old_path.clone();
+        }
+        self.status = self.status.max(other.status);
+        self.commits += other.commits;
+        self.additions += other.additions;
+        self.deletions += other.deletions;
+    }
+}
+
+// === 报告相关类型 ===
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FileStatsReport {
+    by_project: Vec<ProjectFileStats>,
+    by_author: Vec<AuthorFileStats>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProjectFileStats {
+    project: String,
+    lines: u32,
+    files: Vec<FileStatEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AuthorFileStats {
+    author: String,
+    email: String,
+    lines: u32,
+    files: Vec<FileStatEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FileStatEntry {
+    project: String,
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    old_path: Option<String>,
+    status: FileStatus,
+    commits: u32,
+    additions: u32,
+    deletions: u32,
+    lines: u32,
+}
+
+impl FileStatEntry {
+    fn new(project: &str, path: &str, stat: &FileStat) -> Self {
+        FileStatEntry {
+            project: project.to_string(),
+            path: path.to_string(),
+            old_path: stat.old_path.clone(),
+            status: stat.status,
+            commits: stat.commits,
+            additions: stat.additions,
+            deletions: stat.deletions,
+            lines: stat.lines(),
+        }
+    }
+}
+
+// 按变更行数排序，只保留前 top 个文件，返回截断前的总行数
+fn sort_and_truncate(files: &mut Vec<FileStatEntry>, top: Option<u32>) -> u32 {
+    let lines = files.iter().map(|f| f.lines).sum();
+    files.sort_by(|a, b| b.lines.cmp(&a.lines).then_with(|| a.path.cmp(&b.path)));
+    if let Some(top) = top {
+        files.truncate(top as usize);
+    }
+    lines
+}
+
+// 生成文件统计，top 限制每个项目和每个作者返回的文件数
+pub fn build_report(author_stats: &HashMap<String, AuthorStats>, top: Option<u32>) -> FileStatsReport {
+    let mut projects: HashMap<&str, HashMap<&str, FileStat>> = HashMap::new();
+    let mut by_author = Vec::new();
+
+    for author_stat in author_stats.values() {
+        let mut files = Vec::new();
+        for (project_name, project_stats) in &author_stat.projects {
+            let project_files = projects.entry(project_name).or_default();
+            for (path, stat) in &project_stats.file_stats {
+                project_files.entry(path).or_default().merge(stat);
+                files.push(FileStatEntry::new(project_name, path, stat));
+            }
+        }
+
+        let lines = sort_and_truncate(&mut files, top);
+        by_author.push(AuthorFileStats {
+            author: author_stat.author_name.clone(),
+            email: author_stat.author_email.clone(),
+            lines,
+            files,
+        });
+    }
+
+    let mut by_project: Vec<_> = projects
+        .into_iter()
+        .map(|(project, files)| {
+            let mut files: Vec<_> = files
+                .iter()
+                .map(|(path, stat)| FileStatEntry::new(project, path, stat))
+                .collect();
+            let lines = sort_and_truncate(&mut files, top);
+            ProjectFileStats {
+                project: project.to_string(),
+                lines,
+                files,
+            }
+        })
+        .collect();
+
+    by_project.sort_by_key(|p| std::cmp::Reverse(p.lines));
+    by_author.sort_by_key(|a| std::cmp::Reverse(a.lines));
+
+    FileStatsReport { by_project, by_author }
+}
@@ -0,0 +1,213 @@
+// 生成文件、第三方代码和压缩文件识别
+// 参考 GitHub linguist 的规则，按文件路径和差异内容判断，命中的文件不计入代码量，排除的行数按类别单独统计
+
+use crate::diff;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+// 包管理器生成的锁文件
+const LOCKFILES: &[&str] = &[
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lockb",
+    "Cargo.lock",
+    "Gemfile.lock",
+    "composer.lock",
+    "poetry.lock",
+    "Pipfile.lock",
+    "uv.lock",
+    "go.sum",
+    "mix.lock",
+    "pubspec.lock",
+    "Podfile.lock",
+    "Package.resolved",
+    "flake.lock",
+    "packages.lock.json",
+    "gradle.lockfile",
+];
+
+// 第三方代码所在的目录，按完整的目录名匹配
+const VENDOR_DIRS: &[&str] = &[
+    "vendor",
+    "vendors",
+    "node_modules",
+    "bower_components",
+    "third_party",
+    "third-party",
+    "thirdparty",
+    "Pods",
+    "Carthage",
+];
+
+// protoc 及其插件生成的文件后缀
+const PROTOBUF_SUFFIXES: &[&str] = &[
+    ".pb.go",
+    ".pb.gw.go",
+    "_pb2.py",
+    "_pb2.pyi",
+    "_pb2_grpc.py",
+    ".pb.cc",
+    ".pb.h",
+    "_pb.js",
+    "_pb.d.ts",
+    "_grpc_pb.js",
+    "_grpc_pb.d.ts",
+    ".pb.swift",
+    ".pb.dart",
+    ".pbenum.dart",
+    ".pbjson.dart",
+    ".pbgrpc.dart",
+];
+
+// 生成代码的标记注释，Go 的 "Code generated ... DO NOT EDIT." 以及 @generated、<auto-generated>
+static GENERATED_MARKER_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"@generated\b|Code generated .* DO NOT EDIT|<auto-generated\b").unwrap()
+});
+
+// 只在差异开头的这些行中查找标记，生成代码的标记一般在文件头部
+const MARKER_SCAN_LINES: usize = 40;
+
+// 新增行平均长度超过该值的 js/css 文件视为压缩文件
+const MINIFIED_LINE_LENGTH: usize = 110;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum GeneratedCategory {
+    Lockfile,
+    Protobuf,
+    Minified,
+    Vendored,
+    GeneratedMarker,
+    // 以下为项目 .gitattributes 中标记的文件
+    LinguistGenerated,
+    LinguistVendored,
+    NoDiff,
+}
+
+// 各类别是否启用，默认全部启用
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct GeneratedDetection {
+    // 锁文件，例如 package-lock.json、Cargo.lock
+    pub lockfile: bool,
+    // protobuf 生成的代码，例如 *.pb.go、*_pb2.py
+    pub protobuf: bool,
+    // 压缩文件和 source map，例如 *.min.js、*.css.map
+    pub minified: bool,
+    // vendor/、node_modules/ 等目录中的第三方代码
+    pub vendored: bool,
+    // 差异中带有 @generated 等生成标记的文件
+    pub generated_marker: bool,
+}
+
+impl Default for GeneratedDetection {
+    fn default() -> Self {
+        GeneratedDetection {
+            lockfile: true,
+            protobuf: true,
+            minified: true,
+            vendored: true,
+            generated_marker: true,
+        }
+    }
+}
+
+impl GeneratedDetection {
+    // 判断文件属于哪个类别，不属于任何启用的类别时返回 None
+    pub fn classify(&self, path: &str, diff: Option<&str>) -> Option<GeneratedCategory> {
+        let file_name = path.rsplit('/').next().unwrap_or(path);
+
+        if self.lockfile && LOCKFILES.contains(&file_name) {
+            return Some(GeneratedCategory::Lockfile);
+        }
+        if self.protobuf && PROTOBUF_SUFFIXES.iter().any(|suffix| file_name.ends_with(suffix)) {
+            return Some(GeneratedCategory::Protobuf);
+        }
+        if self.minified && is_minified(file_name, diff) {
+            return Some(GeneratedCategory::Minified);
+        }
+        if self.vendored {
+            let mut dirs = path.split('/');
+            dirs.next_back();
+            if dirs.any(|dir| VENDOR_DIRS.contains(&dir)) {
+                return Some(GeneratedCategory::Vendored);
+            }
+        }
+        if self.generated_marker
+            && diff.is_some_and(|diff| {
+                diff.lines()
+                    .take(MARKER_SCAN_LINES)
+                    .any(|line| GENERATED_MARKER_RE.is_match(line))
+            })
+        {
+            return Some(GeneratedCategory::GeneratedMarker);
+        }
+        None
+    }
+}
+
+fn is_minified(file_name: &str, diff: Option<&str>) -> bool {
+    let lower = file_name.to_lowercase();
+    if [".min.js", "-min.js", ".min.css", "-min.css", ".min.mjs", ".js.map", ".css.map"]
+        .iter()
+        .any(|suffix| lower.ends_with(suffix))
+    {
+        return true;
+    }
+    if !(lower.ends_with(".js") || lower.ends_with(".mjs") || lower.ends_with(".css")) {
+        return false;
+    }
+
+    // 没有压缩后缀的 js/css 按新增行的平均长度判断
+    let (count, length) = diff::added_lines(diff.unwrap_or(""))
+        .fold((0, 0), |(count, length), line| (count + 1, length + line.len()));
+    count > 0 && length / count > MINIFIED_LINE_LENGTH
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(path: &str, diff: &str) -> Option<GeneratedCategory> {
+        GeneratedDetection::default().classify(path, Some(diff))
+    }
+
+    #[test]
+    fn detects_minified_files_from_long_added_lines() {
+        let long = "x".repeat(200);
+        let diff = format!("--- a/app.js\n+++ b/app.js\n@@ -0,0 +1,1 @@\n+{}\n", long);
+        assert_eq!(classify("dist/app.js", &diff), Some(GeneratedCategory::Minified));
+        assert_eq!(classify("src/app.ts", &diff), None);
+    }
+
+    #[test]
+    fn file_headers_do_not_count_as_added_lines() {
+        // 很长的文件头不影响平均长度
+        let header = format!("--- a/{0}/app.js\n+++ b/{0}/app.js\n", "d".repeat(300));
+        let diff = format!("{}@@ -0,0 +1,2 @@\n+let a = 1;\n+++b;\n", header);
+        assert_eq!(classify("app.js", &diff), None);
+    }
+
+    #[test]
+    fn classifies_by_path() {
+        assert_eq!(classify("web/package-lock.json", ""), Some(GeneratedCategory::Lockfile));
+        assert_eq!(classify("api/user.pb.go", ""), Some(GeneratedCategory::Protobuf));
+        assert_eq!(classify("static/jquery.min.js", ""), Some(GeneratedCategory::Minified));
+        assert_eq!(classify("vendor/lib/a.go", ""), Some(GeneratedCategory::Vendored));
+        assert_eq!(classify("src/vendor.go", ""), None);
+    }
+
+    #[test]
+    fn detects_generated_marker() {
+        let diff = "@@ -0,0 +1,2 @@\n+// Code generated by mockgen. DO NOT EDIT.\n+package mocks\n";
+        assert_eq!(classify("mocks/db.go", diff), Some(GeneratedCategory::GeneratedMarker));
+        let detection = GeneratedDetection {
+            generated_marker: false,
+            ..Default::default()
+        };
+        assert_eq!(detection.classify("mocks/db.go", Some(diff)), None);
+    }
+}
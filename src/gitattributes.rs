@@ -0,0 +1,153 @@
+// 项目 .gitattributes 中的排除规则
+// 读取默认分支中的 .gitattributes，linguist-generated、linguist-vendored 以及 -diff（包括 binary）标记的文件不计入代码量
+// 与 git 一致：规则不匹配目录下的文件（需写成 dir/**），同一属性以最后一条匹配的规则为准，! 表示恢复为未设置
+
+use crate::generated::GeneratedCategory;
+use crate::path_filter;
+use regex::Regex;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Attribute {
+    Generated,
+    Vendored,
+    Diff,
+}
+
+#[derive(Debug)]
+struct Rule {
+    regex: Regex,
+    // None 表示恢复为未设置
+    attributes: Vec<(Attribute, Option<bool>)>,
+}
+
+#[derive(Debug, Default)]
+pub struct GitAttributes {
+    rules: Vec<Rule>,
+}
+
+impl GitAttributes {
+    // 解析 .gitattributes 文本，与排除无关的属性和无法识别的行直接忽略
+    pub fn parse(text: &str) -> Self {
+        let rules = text
+            .lines()
+            .filter_map(|line| {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    return None;
+                }
+                let mut tokens = line.split_whitespace();
+                let pattern = tokens.next()?.trim_matches('"');
+                // git 不支持取反的规则，以 / 结尾的规则不匹配任何文件
+                if pattern.starts_with('!') || pattern.ends_with('/') {
+                    return None;
+                }
+                let attributes: Vec<_> = tokens.filter_map(parse_attribute).collect();
+                if attributes.is_empty() {
+                    return None;
+                }
+                let regex = path_filter::glob_regex(pattern, false).ok()?;
+                Some(Rule { regex, attributes })
+            })
+            .collect();
+
+        GitAttributes { rules }
+    }
+
+    // 文件被 .gitattributes 排除时返回对应的类别
+    pub fn classify(&self, path: &str) -> Option<GeneratedCategory> {
+        if self.get(path, Attribute::Generated) == Some(true) {
+            return Some(GeneratedCategory::LinguistGenerated);
+        }
+        if self.get(path, Attribute::Vendored) == Some(true) {
+            return Some(GeneratedCategory::LinguistVendored);
+        }
+        if self.get(path, Attribute::Diff) == Some(false) {
+            return Some(GeneratedCategory::NoDiff);
+        }
+        None
+    }
+
+    // 最后一条匹配且设置了该属性的规则
+    fn get(&self, path: &str, attribute: Attribute) -> Option<bool> {
+        self.rules
+            .iter()
+            .rev()
+            .filter(|rule| rule.regex.is_match(path))
+            .find_map(|rule| {
+                rule.attributes
+                    .iter()
+                    .rev()
+                    .find(|(name, _)| *name == attribute)
+                    .map(|(_, value)| *value)
+            })
+            .flatten()
+    }
+}
+
+// 解析 attr、-attr、!attr、attr=value 形式的属性，binary 等同于 -diff
+fn parse_attribute(token: &str) -> Option<(Attribute, Option<bool>)> {
+    let (name, value) = match token.split_once('=') {
+        Some((name, value)) => (name, Some(!value.eq_ignore_ascii_case("false"))),
+        None => match token.strip_prefix('-') {
+            Some(name) => (name, Some(false)),
+            None => match token.strip_prefix('!') {
+                Some(name) => (name, None),
+                None => (token, Some(true)),
+            },
+        },
+    };
+    match name {
+        "linguist-generated" => Some((Attribute::Generated, value)),
+        "linguist-vendored" => Some((Attribute::Vendored, value)),
+        "diff" => Some((Attribute::Diff, value)),
+        "binary" if value == Some(true) => Some((Attribute::Diff, Some(false))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_linguist_and_diff_attributes() {
+        let attributes = GitAttributes::parse(
+            "# 生成的代码\n\
+             *.gen.ts linguist-generated\n\
+             third_party/** linguist-vendored=true\n\
+             *.bin binary\n\
+             *.svg -diff\n\
+             *.md text eol=lf\n",
+        );
+        assert_eq!(attributes.classify("src/api.gen.ts"), Some(GeneratedCategory::LinguistGenerated));
+        assert_eq!(attributes.classify("third_party/a/b.c"), Some(GeneratedCategory::LinguistVendored));
+        assert_eq!(attributes.classify("assets/blob.bin"), Some(GeneratedCategory::NoDiff));
+        assert_eq!(attributes.classify("icons/logo.svg"), Some(GeneratedCategory::NoDiff));
+        assert_eq!(attributes.classify("README.md"), None);
+    }
+
+    #[test]
+    fn later_rules_override_earlier_ones() {
+        let attributes = GitAttributes::parse(
+            "generated/** linguist-generated\n\
+             generated/keep.ts -linguist-generated\n\
+             docs/*.html linguist-generated\n\
+             docs/index.html !linguist-generated\n\
+             *.lock linguist-generated=false\n",
+        );
+        assert_eq!(attributes.classify("generated/a.ts"), Some(GeneratedCategory::LinguistGenerated));
+        assert_eq!(attributes.classify("generated/keep.ts"), None);
+        assert_eq!(attributes.classify("docs/api.html"), Some(GeneratedCategory::LinguistGenerated));
+        assert_eq!(attributes.classify("docs/index.html"), None);
+        assert_eq!(attributes.classify("yarn.lock"), None);
+    }
+
+    #[test]
+    fn patterns_do_not_match_directories() {
+        let attributes = GitAttributes::parse("dist linguist-generated\nbuild/ linguist-generated\n!*.js -diff\n");
+        assert_eq!(attributes.classify("dist"), Some(GeneratedCategory::LinguistGenerated));
+        assert_eq!(attributes.classify("dist/app.js"), None);
+        assert_eq!(attributes.classify("build/app.js"), None);
+        assert_eq!(attributes.classify("app.js"), None);
+    }
+}
@@ -0,0 +1,168 @@
+// 作者身份识别
+// 统计按规范化后的邮箱归并作者，同一个人使用不同的名字或邮箱提交时，通过 .mailmap 格式的别名映射合并到同一个身份
+// 支持 git 的四种 .mailmap 写法：
+//   Proper Name <commit@email>
+//   <proper@email> <commit@email>
+//   Proper Name <proper@email> <commit@email>
+//   Proper Name <proper@email> Commit Name <commit@email>
+// 邮箱和名字均不区分大小写
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static MAILMAP_LINE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^([^<]*)<([^>]*)>(?:([^<]*)<([^>]*)>)?").unwrap());
+
+// 规范化后的作者身份
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identity {
+    // 统计使用的 key，优先使用小写邮箱，没有邮箱时使用小写名字
+    pub key: String,
+    pub name: String,
+    pub email: String,
+}
+
+#[derive(Debug, Clone)]
+struct MailmapEntry {
+    proper_name: Option<String>,
+    proper_email: Option<String>,
+    commit_name: Option<String>,
+    // 小写
+    commit_email: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Mailmap {
+    entries: Vec<MailmapEntry>,
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+impl Mailmap {
+    // 解析 .mailmap 文本，无法识别的行直接忽略
+    pub fn parse(text: &str) -> Self {
+        let entries = text
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or("").trim())
+            .filter_map(|line| MAILMAP_LINE_RE.captures(line))
+            .map(|captures| {
+                let first_name = non_empty(&captures[1]);
+                let first_email = non_empty(&captures[2]);
+                match captures.get(4) {
+                    Some(commit_email) => MailmapEntry {
+                        proper_name: first_name,
+                        proper_email: first_email,
+                        commit_name: captures.get(3).and_then(|m| non_empty(m.as_str())),
+                        commit_email: commit_email.as_str().trim().to_lowercase(),
+                    },
+                    // 只有一个邮箱时只替换名字
+                    None => MailmapEntry {
+                        proper_name: first_name,
+                        proper_email: None,
+                        commit_name: None,
+                        commit_email: first_email.unwrap_or_default().to_lowercase(),
+                    },
+                }
+            })
+            .collect();
+
+        Mailmap { entries }
+    }
+
+    // 合并另一个映射，冲突时 other 中的条目优先
+    pub fn merged(&self, other: &Mailmap) -> Mailmap {
+        let mut entries = self.entries.clone();
+        entries.extend(other.entries.iter().cloned());
+        Mailmap { entries }
+    }
+
+    // 解析提交作者的规范身份，同时匹配名字和邮箱的条目优先，同类条目中后出现的优先
+    pub fn resolve(&self, name: &str, email: &str) -> Identity {
+        let lower_email = email.trim().to_lowercase();
+        let matched = self
+            .entries
+            .iter()
+            .filter(|entry| entry.commit_email == lower_email)
+            .filter(|entry| {
+                entry
+                    .commit_name
+                    .as_ref()
+                    .is_none_or(|commit_name| commit_name.eq_ignore_ascii_case(name.trim()))
+            })
+            // 相同优先级时 max_by_key 返回最后一个
+            .max_by_key(|entry| entry.commit_name.is_some());
+
+        let name = matched
+            .and_then(|entry| entry.proper_name.clone())
+            .unwrap_or_else(|| name.trim().to_string());
+        let email = matched
+            .and_then(|entry| entry.proper_email.clone())
+            .unwrap_or_else(|| email.trim().to_string());
+        let key = if email.is_empty() {
+            name.to_lowercase()
+        } else {
+            email.to_lowercase()
+        };
+
+        Identity { key, name, email }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAILMAP: &str = "\
+# 团队成员
+Alice Liu <alice@corp.com>
+<bob@corp.com> <bob@home.net>
+Carol Wu <carol@corp.com> <CAROL@old.com>
+Dave Li <dave@corp.com> dave <shared@corp.com>
+Dave Bot <ci@corp.com> <shared@corp.com>
+";
+
+    fn resolve(name: &str, email: &str) -> (String, String, String) {
+        let identity = Mailmap::parse(MAILMAP).resolve(name, email);
+        (identity.key, identity.name, identity.email)
+    }
+
+    fn identity(key: &str, name: &str, email: &str) -> (String, String, String) {
+        (key.to_string(), name.to_string(), email.to_string())
+    }
+
+    #[test]
+    fn replaces_name_only() {
+        assert_eq!(resolve("alice", "Alice@Corp.com"), identity("alice@corp.com", "Alice Liu", "Alice@Corp.com"));
+    }
+
+    #[test]
+    fn replaces_email_only() {
+        assert_eq!(resolve("Bob", "bob@home.net"), identity("bob@corp.com", "Bob", "bob@corp.com"));
+    }
+
+    #[test]
+    fn replaces_name_and_email_case_insensitively() {
+        assert_eq!(resolve("carol", "carol@OLD.com"), identity("carol@corp.com", "Carol Wu", "carol@corp.com"));
+    }
+
+    #[test]
+    fn entries_with_commit_name_take_precedence() {
+        assert_eq!(resolve("Dave", "shared@corp.com"), identity("dave@corp.com", "Dave Li", "dave@corp.com"));
+        assert_eq!(resolve("deploy", "shared@corp.com"), identity("ci@corp.com", "Dave Bot", "ci@corp.com"));
+    }
+
+    #[test]
+    fn unmatched_authors_are_normalized() {
+        assert_eq!(resolve(" Eve ", " Eve@Example.com "), identity("eve@example.com", "Eve", "Eve@Example.com"));
+        assert_eq!(resolve("Eve", ""), identity("eve", "Eve", ""));
+    }
+
+    #[test]
+    fn later_mailmap_wins_when_merged() {
+        let project = Mailmap::parse("Alice L. <alice@corp.com>");
+        let identity = Mailmap::parse(MAILMAP).merged(&project).resolve("alice", "alice@corp.com");
+        assert_eq!(identity.name, "Alice L.");
+    }
+}
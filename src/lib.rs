@@ -1,15 +1,63 @@
-// 该项目仅支持 web 环境
-
-use futures::future::join_all;
-use js_sys::{Array, Promise, Date, Object, Reflect};
+// 默认运行在 web 环境，启用 node feature 后可运行在 Node 环境
+// 所有请求都通过 transport::Transport 发起，核心流程不依赖 window
+
+mod bots;
+pub mod cache;
+pub mod cancel;
+pub mod checkpoint;
+mod coauthor;
+mod conventional;
+mod dedup;
+mod diff;
+mod file_stats;
+mod generated;
+mod gitattributes;
+mod identity;
+mod limiter;
+mod ownership;
+mod pagination;
+mod path_filter;
+pub mod platform;
+pub mod progress;
+mod retry;
+mod revert;
+pub mod transport;
+mod users;
+
+#[cfg(test)]
+mod tests;
+
+use bots::{BotDetector, BotPolicy};
+use cache::{CacheKind, ResponseCache};
+use cancel::CancelToken;
+use checkpoint::{Checkpoint, CheckpointCallback, CheckpointState};
+use coauthor::CoauthorPolicy;
+use conventional::{CommitTypeReport, ConventionalCommit};
+use dedup::{DedupPolicy, DedupProject};
+use file_stats::{FileChange, FileStat, FileStatsReport, FileStatus};
+use futures::future::{join_all, try_join, FutureExt};
+use generated::{GeneratedCategory, GeneratedDetection};
+use gitattributes::GitAttributes;
+use identity::{Identity, Mailmap};
+use limiter::Limiter;
+use ownership::ProjectOwnership;
+use path_filter::PathFilter;
 use once_cell::sync::Lazy;
+use platform::{log, log_error, now_ms};
+use progress::{ProgressCallback, ProgressEvent, ProgressTracker};
 use regex::Regex;
+use retry::RateLimitState;
+use revert::{RevertPolicy, Reverts};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+use std::sync::Mutex;
+use transport::{HttpRequest, HttpResponse, Transport, TransportError};
+use users::{AuthorIdentity, GitlabUser};
 use wasm_bindgen::prelude::*;
-use wasm_bindgen_futures::JsFuture;
-use web_sys::{Request, RequestInit, RequestMode, Response, console, AbortController, Headers, window};
 
 // === 配置相关类型 ===
 #[derive(Serialize, Deserialize)]
@@ -24,6 +72,150 @@ pub struct Config {
     valid_extensions: Vec<String>,
     max_concurrent_requests: u32,
     ignored_paths: Vec<String>,
+    #[serde(default)]
+    retry: RetryConfig,
+    // 每处理多少个提交导出一次断点，0 表示只在项目完成时导出
+    #[serde(default = "default_checkpoint_interval")]
+    checkpoint_interval: u32,
+    // 从断点恢复分析
+    #[serde(default)]
+    resume_from: Option<Checkpoint>,
+    // 快速模式，直接使用提交列表接口返回的增删行数，不再逐个下载提交差异
+    #[serde(default)]
+    fast_mode: bool,
+    // 文件统计中每个项目、每个作者最多返回的文件数，不配置时返回全部
+    #[serde(default)]
+    file_stats_top: Option<u32>,
+    // 目录归属统计的目录层级，0 表示不统计
+    #[serde(default = "default_ownership_depth")]
+    ownership_depth: u32,
+    // .mailmap 格式的作者别名映射，用于合并同一个人的不同名字和邮箱
+    #[serde(default)]
+    mailmap: Option<String>,
+    // 是否读取各项目默认分支中的 .mailmap 文件，与 mailmap 冲突时以 mailmap 为准
+    #[serde(default)]
+    fetch_mailmap: bool,
+    // 是否把作者匹配到 GitLab 账号
+    #[serde(default)]
+    resolve_users: bool,
+    // 多个项目中出现相同 sha 的提交时的归属策略
+    #[serde(default)]
+    dedup_policy: DedupPolicy,
+    // 合并提交的统计方式
+    #[serde(default)]
+    merge_policy: MergePolicy,
+    // 回滚提交与被回滚提交的统计方式
+    #[serde(default)]
+    revert_policy: RevertPolicy,
+    // 提交信息中 Co-authored-by 标注的共同作者的统计方式
+    #[serde(default)]
+    coauthor_policy: CoauthorPolicy,
+    // 识别机器人的正则，匹配作者名或邮箱，不区分大小写，不配置时使用 bots::DEFAULT_BOT_PATTERNS
+    #[serde(default)]
+    bot_patterns: Option<Vec<String>>,
+    // 机器人提交的统计方式
+    #[serde(default)]
+    bot_policy: BotPolicy,
+    // 生成文件、第三方代码、压缩文件的识别开关，识别出的文件不计入代码量
+    #[serde(default)]
+    generated_detection: GeneratedDetection,
+    // 是否读取各项目默认分支中的 .gitattributes，排除 linguist-generated、linguist-vendored 和 -diff 标记的文件
+    #[serde(default = "default_fetch_gitattributes")]
+    fetch_gitattributes: bool,
+}
+
+// 合并提交的差异包含了被合并分支的全部改动，按代码行统计会夸大点击合并的人的贡献
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum MergePolicy {
+    // 不统计合并提交
+    Exclude,
+    // 计入提交数，但不统计代码行
+    #[default]
+    CountWithoutLines,
+    // 计入提交数，代码行单独记录在 mergeLines 中
+    Separate,
+}
+
+fn default_checkpoint_interval() -> u32 {
+    checkpoint::DEFAULT_CHECKPOINT_INTERVAL
+}
+
+fn default_ownership_depth() -> u32 {
+    ownership::DEFAULT_OWNERSHIP_DEPTH
+}
+
+fn default_fetch_gitattributes() -> bool {
+    true
+}
+
+impl Config {
+    // 提交列表接口只有整体的增删行数，配置了扩展名或路径过滤时仍需逐个下载差异
+    fn uses_commit_stats(&self) -> bool {
+        self.fast_mode && self.valid_extensions.is_empty() && self.ignored_paths.is_empty()
+    }
+}
+
+// 请求超时与重试配置，未配置的字段使用默认值
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct RetryConfig {
+    // 各类接口的超时时间（毫秒）
+    timeout: TimeoutConfig,
+    // 最大请求次数（包含首次请求）
+    max_attempts: u32,
+    // 指数退避的初始等待时间（毫秒）
+    base_backoff: u32,
+    // 指数退避的最大等待时间（毫秒）
+    max_backoff: u32,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            timeout: TimeoutConfig::default(),
+            max_attempts: 20,
+            base_backoff: retry::DEFAULT_BASE_BACKOFF,
+            max_backoff: retry::DEFAULT_MAX_BACKOFF,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct TimeoutConfig {
+    projects: i32,
+    commits: i32,
+    diffs: i32,
+    refs: i32,
+    files: i32,
+    users: i32,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        TimeoutConfig {
+            projects: 5000,
+            commits: 5000,
+            diffs: 5000,
+            refs: 5000,
+            files: 5000,
+            users: 5000,
+        }
+    }
+}
+
+impl TimeoutConfig {
+    fn for_kind(&self, kind: RequestKind) -> i32 {
+        match kind {
+            RequestKind::Projects => self.projects,
+            RequestKind::Commits => self.commits,
+            RequestKind::Diffs => self.diffs,
+            RequestKind::Refs => self.refs,
+            RequestKind::Files => self.files,
+            RequestKind::Users => self.users,
+        }
+    }
 }
 
 // === 统计相关类型 ===
@@ -34,9 +226,33 @@ struct Stats {
     lines: u32,
     files: u32,
     size: u64,
+    hunks: u32,
+    // 每个文件的变更
+    file_changes: Vec<FileChange>,
+    // 按类别统计的被识别为生成文件而排除的行数
+    excluded_lines: BTreeMap<GeneratedCategory, u32>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl Stats {
+    // 代码行平均分给 parts 个作者时第 index 个作者的部分，涉及的文件数不拆分
+    // 文件变更和排除的行数只记在主作者名下，不参与分配
+    fn share(&self, parts: usize, index: usize) -> Stats {
+        let share = |value: u32| coauthor::share(value as u64, parts, index) as u32;
+        let additions = share(self.additions);
+        let deletions = share(self.deletions);
+        Stats {
+            additions,
+            deletions,
+            lines: additions + deletions,
+            files: self.files,
+            size: coauthor::share(self.size, parts, index),
+            hunks: share(self.hunks),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
 struct AuthorStats {
     author_name: String,
     author_email: String,
@@ -47,10 +263,20 @@ struct AuthorStats {
     total_lines: u32,
     total_files: u32,
     total_size: u64,
+    #[serde(default)]
+    total_hunks: u32,
+    #[serde(default)]
+    total_merges: u32,
+    #[serde(default)]
+    total_merge_lines: u32,
+    #[serde(default)]
+    total_reverted_lines: u32,
+    #[serde(default)]
+    total_co_authored: u32,
     commit_details: Vec<CommitDetail>,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
 struct ProjectStats {
     commits: u32,
     additions: u32,
@@ -58,6 +284,26 @@ struct ProjectStats {
     lines: u32,
     files: u32,
     size: u64,
+    #[serde(default)]
+    hunks: u32,
+    // 合并提交数
+    #[serde(default)]
+    merges: u32,
+    // MergePolicy::Separate 时合并提交的代码行
+    #[serde(default)]
+    merge_lines: u32,
+    // 被其他提交回滚的代码行
+    #[serde(default)]
+    reverted_lines: u32,
+    // 有共同作者的提交数
+    #[serde(default)]
+    co_authored: u32,
+    // 按类别统计的生成文件排除行数
+    #[serde(default)]
+    excluded_lines: BTreeMap<GeneratedCategory, u32>,
+    // 按文件路径累计的变更
+    #[serde(default)]
+    file_stats: HashMap<String, FileStat>,
 }
 
 // === 错误处理相关类型 ===
@@ -70,6 +316,42 @@ pub struct FailureRecord {
     pub error: String,
 }
 
+// 分析流程中的错误，在 wasm 边界处转换为 JsValue
+#[derive(Debug, Clone)]
+pub enum AnalysisError {
+    // 请求多次重试后依旧失败
+    Request(String),
+    // 响应解析失败
+    Parse(String),
+    // 分析被取消
+    Cancelled,
+    // 配置错误
+    Config(String),
+}
+
+impl fmt::Display for AnalysisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnalysisError::Request(msg) => write!(f, "{}", msg),
+            AnalysisError::Parse(msg) => write!(f, "解析响应失败: {}", msg),
+            AnalysisError::Cancelled => write!(f, "分析已取消"),
+            AnalysisError::Config(msg) => write!(f, "配置错误: {}", msg),
+        }
+    }
+}
+
+impl From<serde_json::Error> for AnalysisError {
+    fn from(e: serde_json::Error) -> Self {
+        AnalysisError::Parse(e.to_string())
+    }
+}
+
+impl From<AnalysisError> for JsValue {
+    fn from(e: AnalysisError) -> Self {
+        JsValue::from_str(&e.to_string())
+    }
+}
+
 // === HTTP 请求相关类型 ===
 struct RequestConfig {
     url: String,
@@ -77,11 +359,64 @@ struct RequestConfig {
     operation: String,
     timeout: i32,
     retries: u32,
+    base_backoff: u32,
+    max_backoff: u32,
 }
 
-enum ResponseResult {
-    Success(JsValue),
-    Retry,
+// 请求对应的接口类型
+#[derive(Clone, Copy, Debug)]
+enum RequestKind {
+    Projects,
+    Commits,
+    Diffs,
+    Refs,
+    // 仓库中的文件，例如 .mailmap
+    Files,
+    // 用户和群组成员
+    Users,
+}
+
+// 请求的上下文信息，用于记录失败详情
+struct RequestContext {
+    kind: RequestKind,
+    operation: String,
+    project_name: Option<String>,
+    author: Option<String>,
+}
+
+// 一次分析过程中共享的状态
+struct AnalysisContext {
+    config: Config,
+    transport: Rc<dyn Transport>,
+    author_stats: Mutex<HashMap<String, AuthorStats>>,
+    failure_stats: Mutex<Vec<FailureRecord>>,
+    rate_limit: RateLimitState,
+    // 限制同时进行中的请求数
+    limiter: Limiter,
+    progress: ProgressTracker,
+    cancel: CancelToken,
+    checkpoint: CheckpointState,
+    on_checkpoint: Option<CheckpointCallback>,
+    cache: Option<Rc<dyn ResponseCache>>,
+    // Config.mailmap 解析后的别名映射
+    mailmap: Mailmap,
+    // 根据 Config.bot_patterns 识别机器人
+    bots: BotDetector,
+    // valid_extensions 和 ignored_paths 编译后的规则
+    path_filter: PathFilter,
+}
+
+// 无法通过 serde 传入的运行时选项，例如 js 回调
+#[derive(Default)]
+pub struct AnalysisOptions {
+    // 进度事件回调
+    pub on_progress: Option<ProgressCallback>,
+    // 取消分析，取消后返回已完成部分的报告
+    pub cancel: Option<CancelToken>,
+    // 断点导出回调
+    pub on_checkpoint: Option<CheckpointCallback>,
+    // 提交差异的响应缓存
+    pub cache: Option<Rc<dyn ResponseCache>>,
 }
 
 // === GitLab API 相关类型 ===
@@ -89,15 +424,50 @@ enum ResponseResult {
 struct Project {
     id: i64,
     name: String,
+    // 空仓库没有默认分支
+    #[serde(default)]
+    default_branch: Option<String>,
+    // fork 的来源项目
+    #[serde(default)]
+    forked_from_project: Option<ForkedFrom>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
+struct ForkedFrom {
+    id: i64,
+}
+
+// 分页获取到的数据
+struct Paginated<T> {
+    items: Vec<T>,
+    // 数据总数，未知时为空
+    total: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 struct Commit {
     id: String,
     author_email: String,
     author_name: String,
     message: String,
     committed_date: String,
+    // 请求时带上 with_stats=true 才会返回
+    #[serde(default)]
+    stats: Option<CommitStats>,
+    #[serde(default)]
+    parent_ids: Vec<String>,
+}
+
+impl Commit {
+    fn is_merge(&self) -> bool {
+        self.parent_ids.len() > 1
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct CommitStats {
+    additions: u32,
+    deletions: u32,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -105,6 +475,12 @@ struct DiffInfo {
     old_path: Option<String>,
     new_path: Option<String>,
     diff: Option<String>,
+    #[serde(default)]
+    new_file: bool,
+    #[serde(default)]
+    renamed_file: bool,
+    #[serde(default)]
+    deleted_file: bool,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -122,13 +498,58 @@ struct RefInfo {
 
 // === 报告相关类型 ===
 #[derive(Serialize, Deserialize, Debug)]
-struct Report {
+pub struct Report {
     #[serde(rename = "codeStats")]
     code_stats: Vec<CodeStat>,
+    // 机器人的代码统计，bot_policy 为 separate 时返回，其他统计中都不包含机器人
+    #[serde(rename = "botStats", skip_serializing_if = "Option::is_none")]
+    bot_stats: Option<Vec<CodeStat>>,
     #[serde(rename = "commitStats")]
     commit_stats: Vec<CommitStat>,
+    #[serde(rename = "projectSummary")]
+    project_summary: ProjectSummary,
     #[serde(rename = "failureStats", skip_serializing_if = "Option::is_none")]
     failure_stats: Option<Vec<FailureRecord>>,
+    #[serde(rename = "fileStats")]
+    file_stats: FileStatsReport,
+    // 按作者和项目统计的提交类型
+    #[serde(rename = "commitTypes")]
+    commit_types: CommitTypeReport,
+    // 每个项目的目录归属树
+    ownership: Vec<ProjectOwnership>,
+    // 因为在多个项目中重复出现而没有重复统计的提交数
+    #[serde(rename = "duplicatesDropped")]
+    duplicates_dropped: u32,
+    // 合并提交总数，包括 merge_policy 为 exclude 时没有统计的部分
+    #[serde(rename = "mergeCommits")]
+    merge_commits: u32,
+    // 按类别统计的被识别为生成文件而排除的行数
+    #[serde(rename = "excludedLines")]
+    excluded_lines: BTreeMap<GeneratedCategory, u32>,
+    // 未匹配到 GitLab 账号的作者，开启 resolve_users 时返回
+    #[serde(rename = "unmatchedAuthors", skip_serializing_if = "Option::is_none")]
+    unmatched_authors: Option<Vec<UnmatchedAuthor>>,
+    // 分析是否被取消，取消时报告只包含已完成的部分
+    cancelled: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct UnmatchedAuthor {
+    author: String,
+    email: String,
+}
+
+// 项目数量汇总
+#[derive(Serialize, Deserialize, Debug)]
+struct ProjectSummary {
+    // 群组下的项目总数，GitLab 未返回 X-Total 且未取完所有页时为空
+    total: Option<u32>,
+    // 实际获取到的项目数（受 projects_num 限制）
+    fetched: u32,
+    // 被 excluded_projects 排除的项目数
+    excluded: u32,
+    // 实际分析的项目数
+    analyzed: u32,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -143,6 +564,20 @@ struct CodeStat {
     lines: u32,
     files: u32,
     size: u64,
+    hunks: u32,
+    // 合并提交数，已计入 commits
+    merges: u32,
+    #[serde(rename = "mergeLines")]
+    merge_lines: u32,
+    // 被其他提交回滚的代码行，revert_policy 不为 count 时不计入 lines
+    #[serde(rename = "revertedLines")]
+    reverted_lines: u32,
+    // 有共同作者的提交数，已计入 commits
+    #[serde(rename = "coAuthored")]
+    co_authored: u32,
+    // 匹配到的 GitLab 账号，只在总计数据中返回
+    #[serde(skip_serializing_if = "Option::is_none", rename = "gitlabUser")]
+    gitlab_user: Option<GitlabUser>,
     #[serde(skip_serializing_if = "Option::is_none", rename = "isTotal")]
     is_total: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -159,9 +594,12 @@ struct CommitStat {
     #[serde(rename = "committedDate")]
     committed_date: String,
     message: String,
+    // 按 Conventional Commits 解析的提交信息，不符合格式时为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    conventional: Option<ConventionalCommit>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 struct CommitDetail {
     project: String,
     branch: String,
@@ -174,256 +612,486 @@ struct CommitDetail {
 static MERGE_BRANCH_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"Merge branch '([^']+)'").unwrap());
 
 // === 实现部分 ===
-// 通用的请求构建函数
-fn build_request(config: &RequestConfig) -> Result<(Request, AbortController), JsValue> {
-    let opts = RequestInit::new();
-    opts.set_method("GET");
-    opts.set_mode(RequestMode::Cors);
-
-    let headers = Headers::new()?;
-    headers.append("Private-Token", &config.token)?;
-    headers.append("Content-Type", "application/json")?;
-    opts.set_headers(&headers);
-
-    let abort_controller = AbortController::new()?;
-    opts.set_signal(Some(&abort_controller.signal()));
-
-    let request = Request::new_with_str_and_init(&config.url, &opts)?;
-
-    Ok((request, abort_controller))
-}
-
 // 添加错误类型枚举
 enum RequestError<'a> {
-    Http(&'a Response),
+    Http(&'a HttpResponse),
     Timeout(&'a str),
     Network(&'a str),
 }
 
-// 合并的错误日志记录函数
+// 去掉 api 前缀，只保留 v4/ 之后的路径
+fn short_url(url: &str) -> String {
+    match url.find("v4/") {
+        Some(index) => url[index..].to_string(),
+        None => url.to_string(),
+    }
+}
+
+// 合并的错误日志记录函数，is_final 为 true 时记录到失败统计中
 fn log_request_error(
     error: RequestError,
     duration: f64,
     config: &RequestConfig,
-    failure_stats: &Arc<Mutex<Vec<FailureRecord>>>,
-    retry_count: u32,
-    project_name: Option<String>,
-    author: Option<String>,
+    context: &RequestContext,
+    ctx: &AnalysisContext,
+    is_final: bool,
 ) {
     let error_msg = match &error {
         RequestError::Http(response) => {
-            format!("HTTP error! status: {} {}", response.status(), response.status_text())
+            format!("HTTP error! status: {} {}", response.status, response.status_text)
         }
         RequestError::Timeout(msg) => msg.to_string(),
         RequestError::Network(msg) => format!("网络错误: {}", msg),
     };
 
-    console::error_1(&format!("[请求失败] 耗时: {}ms", duration).into());
-    console::error_1(&format!("错误信息: {}", error_msg).into());
+    log_error(&format!("[请求失败] 耗时: {}ms", duration));
+    log_error(&format!("错误信息: {}", error_msg));
 
-    if retry_count >= config.retries {
+    if is_final {
         let failure_record = FailureRecord {
-            url: config.url.replace(config.url.split_at(config.url.find("v4/").unwrap()).0, ""),
-            project_name,
-            author,
+            url: short_url(&config.url),
+            project_name: context.project_name.clone(),
+            author: context.author.clone(),
             operation: config.operation.clone(),
             error: error_msg,
         };
 
-        if let Ok(mut stats) = failure_stats.lock() {
+        ctx.progress.emit(ProgressEvent::Failure {
+            operation: failure_record.operation.clone(),
+            url: failure_record.url.clone(),
+            project: failure_record.project_name.clone(),
+            error: failure_record.error.clone(),
+        });
 
+        if let Ok(mut stats) = ctx.failure_stats.lock() {
             stats.push(failure_record);
         }
     }
 }
 
-// 修改 handle_response 函数中的错误处理
-async fn handle_response(
-    response: Result<JsValue, JsValue>,
-    start_time: &f64,
-    abort_controller: &AbortController,
-    config: &RequestConfig,
-    retry_count: &u32,
-    failure_stats: &Arc<Mutex<Vec<FailureRecord>>>,
-    project_name: Option<String>,
-    author: Option<String>,
-) -> Result<ResponseResult, JsValue> {
-    let end_time = Date::now();
-    let duration = end_time - start_time;
-
-    match response {
-        Ok(response) => {
-            if let Some(timeout_val) = check_timeout(&response)? {
-                if timeout_val.is_truthy() {
-                    abort_controller.abort();
-                    console::log_1(
-                        &format!(
-                            "URL: {}",
-                            config.url.replace(config.url.split_at(config.url.find("v4/").unwrap()).0, "")
-                        )
-                        .into(),
-                    );
-                    let timeout_msg = format!(
-                        "{}，第{}次请求失败，请求超时{}",
-                        config.operation,
-                        retry_count + 1,
-                        if retry_count + 1 < config.retries { "，开始重试..." } else { "" },
-                    );
-                    log_request_error(
-                        RequestError::Timeout(&timeout_msg),
-                        duration,
-                        config,
-                        failure_stats,
-                        *retry_count + 1,
-                        project_name,
-                        author,
-                    );
-                    return Ok(ResponseResult::Retry);
-                }
+// 分段等待，取消后尽快返回
+async fn sleep_cancellable(ctx: &AnalysisContext, ms: f64) {
+    const SLICE: f64 = 500.0;
+    let until = now_ms() + ms;
+    while !ctx.cancel.is_cancelled() {
+        let remaining = until - now_ms();
+        if remaining <= 0.0 {
+            break;
+        }
+        ctx.transport.sleep(remaining.min(SLICE).ceil() as i32).await;
+    }
+}
+
+// 触发限流时等待，直到全局暂停结束
+async fn wait_for_rate_limit(ctx: &AnalysisContext) {
+    while !ctx.cancel.is_cancelled() {
+        let wait = ctx.rate_limit.wait_time(now_ms());
+        if wait <= 0.0 {
+            break;
+        }
+        sleep_cancellable(ctx, wait).await;
+    }
+}
+
+// 发起请求，包含失败重试逻辑
+async fn fetch_with_retry(
+    ctx: &AnalysisContext,
+    url: &str,
+    context: &RequestContext,
+) -> Result<HttpResponse, AnalysisError> {
+    let retry_config = &ctx.config.retry;
+    let config = RequestConfig {
+        url: url.to_string(),
+        token: ctx.config.gitlab_token.clone(),
+        operation: context.operation.clone(),
+        timeout: retry_config.timeout.for_kind(context.kind),
+        retries: retry_config.max_attempts.max(1),
+        base_backoff: retry_config.base_backoff,
+        max_backoff: retry_config.max_backoff,
+    };
+
+    let request = HttpRequest {
+        url: config.url.clone(),
+        headers: vec![
+            ("Private-Token".to_string(), config.token.clone()),
+            ("Content-Type".to_string(), "application/json".to_string()),
+        ],
+        timeout: config.timeout,
+        cancel: ctx.cancel.clone(),
+    };
+
+    for retry_count in 1..=config.retries {
+        // 只在请求期间占用许可，重试等待时让给其他请求
+        let permit = loop {
+            wait_for_rate_limit(ctx).await;
+            let permit = ctx.limiter.acquire().await;
+            // 排队期间其他请求可能触发了限流，归还许可后继续等待
+            if ctx.cancel.is_cancelled() || ctx.rate_limit.wait_time(now_ms()) <= 0.0 {
+                break permit;
             }
+        };
+        if ctx.cancel.is_cancelled() {
+            return Err(AnalysisError::Cancelled);
+        }
 
-            let response: Response = response.dyn_into()?;
-            if response.ok() {
-                let json_promise = response.json()?;
-                let json_value = JsFuture::from(json_promise).await?;
-                Ok(ResponseResult::Success(json_value))
-            } else {
+        let start_time = now_ms();
+        let result = ctx.transport.fetch(&request).await;
+        let duration = now_ms() - start_time;
+        drop(permit);
+        let is_last = retry_count >= config.retries;
+        // 是否已经因为限流暂停了所有请求
+        let mut paused = false;
+
+        match result {
+            Ok(response) => {
+                if let Some(until) = ctx.rate_limit.update(&response, now_ms()) {
+                    paused = true;
+                    log(&format!(
+                        "[触发限流] 暂停所有请求 {}ms",
+                        (until - now_ms()).max(0.0).round()
+                    ));
+                }
+
+                // 仓库中的文件不存在属于正常情况，由调用方处理
+                let missing_file = matches!(context.kind, RequestKind::Files) && response.status == 404;
+                if response.ok() || missing_file {
+                    return Ok(response);
+                }
+
+                // 鉴权失败、资源不存在等状态码直接失败，不再重试
+                let retryable = retry::is_retryable_status(response.status);
                 log_request_error(
                     RequestError::Http(&response),
                     duration,
-                    config,
-                    failure_stats,
-                    *retry_count + 1,
-                    project_name,
-                    author,
+                    &config,
+                    context,
+                    ctx,
+                    is_last || !retryable,
+                );
+                if !retryable {
+                    return Err(AnalysisError::Request(format!(
+                        "{}失败，状态码 {}，不再重试",
+                        config.operation, response.status
+                    )));
+                }
+            }
+            Err(TransportError::Timeout) => {
+                log(&format!("URL: {}", short_url(&config.url)));
+                let timeout_msg = format!(
+                    "{}，第{}次请求失败，请求超时{}",
+                    config.operation,
+                    retry_count,
+                    if !is_last { "，开始重试..." } else { "" },
+                );
+                log_request_error(
+                    RequestError::Timeout(&timeout_msg),
+                    duration,
+                    &config,
+                    context,
+                    ctx,
+                    is_last,
+                );
+            }
+            Err(TransportError::Aborted) => return Err(AnalysisError::Cancelled),
+            Err(TransportError::Network(msg)) => {
+                log_request_error(
+                    RequestError::Network(&msg),
+                    duration,
+                    &config,
+                    context,
+                    ctx,
+                    is_last,
                 );
-                Ok(ResponseResult::Retry)
             }
         }
-        Err(e) => {
-            abort_controller.abort();
-            log_request_error(
-                RequestError::Network(&format!("{:?}", e)),
-                duration,
-                config,
-                failure_stats,
-                *retry_count + 1,
-                project_name,
-                author,
-            );
-            Ok(ResponseResult::Retry)
+
+        if !is_last {
+            // 限流时由 wait_for_rate_limit 统一等待，否则按指数退避等待后重试
+            let delay = if paused {
+                ctx.rate_limit.wait_time(now_ms())
+            } else {
+                retry::backoff_delay(retry_count, config.base_backoff, config.max_backoff) as f64
+            };
+            ctx.progress.emit(ProgressEvent::Retry {
+                operation: config.operation.clone(),
+                url: short_url(&config.url),
+                attempt: retry_count,
+                max_attempts: config.retries,
+                delay_ms: delay,
+            });
+            if !paused {
+                sleep_cancellable(ctx, delay).await;
+            }
         }
     }
+
+    Err(AnalysisError::Request("达到最大重试次数".to_string()))
 }
 
-// 辅助函数：检查是否超时
-fn check_timeout(response: &JsValue) -> Result<Option<JsValue>, JsValue> {
-    if response.is_object() {
-        let timeout_obj = Object::from(response.clone());
-        if let Ok(timeout_val) = Reflect::get(&timeout_obj, &"timeout".into()) {
-            return Ok(Some(timeout_val));
-        }
-    }
-    Ok(None)
+// 请求并解析 json 响应
+async fn fetch_json<T: DeserializeOwned>(
+    ctx: &AnalysisContext,
+    url: &str,
+    context: &RequestContext,
+) -> Result<T, AnalysisError> {
+    let response = fetch_with_retry(ctx, url, context).await?;
+    Ok(serde_json::from_str(&response.body)?)
 }
 
-// 添加一个辅助函数来创建延迟
-async fn delay(ms: i32) {
-    let promise = Promise::new(&mut |resolve, _| {
-        window()
-            .unwrap()
-            .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms)
-            .unwrap();
-    });
-    JsFuture::from(promise).await.unwrap();
+// 优先从缓存中读取响应，未命中时请求并写入缓存
+async fn fetch_json_cached<T: DeserializeOwned>(
+    ctx: &AnalysisContext,
+    url: &str,
+    cache_key: &str,
+    context: &RequestContext,
+) -> Result<T, AnalysisError> {
+    let Some(cache) = &ctx.cache else {
+        return fetch_json(ctx, url, context).await;
+    };
+
+    match cache.get(cache_key).await {
+        Ok(Some(body)) => match serde_json::from_str(&body) {
+            Ok(value) => return Ok(value),
+            Err(e) => log_error(&format!("[缓存] 解析缓存失败，重新请求: {} {}", cache_key, e)),
+        },
+        Ok(None) => {}
+        Err(e) => log_error(&format!("[缓存] 读取缓存失败: {} {}", cache_key, e)),
+    }
+
+    let response = fetch_with_retry(ctx, url, context).await?;
+    let value = serde_json::from_str(&response.body)?;
+    if let Err(e) = cache.put(cache_key, &response.body).await {
+        log_error(&format!("[缓存] 写入缓存失败: {} {}", cache_key, e));
+    }
+    Ok(value)
 }
 
-// 发起请求，包含失败重试逻辑
-pub async fn fetch_with_retry(
-  url: &str,
-  token: &str,
-  context: &JsValue,
-  failure_stats: &Arc<Mutex<Vec<FailureRecord>>>,
-) -> Result<JsValue, JsValue> {
-  let details = Reflect::get(context, &"details".into())?;
-  let operation = Reflect::get(&details, &"operation".into())?
-      .as_string()
-      .unwrap_or_default();
-  let project_name = Reflect::get(&details, &"projectName".into())
-      .ok()
-      .and_then(|v| v.as_string());
-  let author = Reflect::get(&details, &"authorEmail".into())
-      .ok()
-      .and_then(|v| v.as_string());
-
-  let config = RequestConfig {
-      url: url.to_string(),
-      token: token.to_string(),
-      operation: operation.clone(),
-      timeout: 5000, // 超时时间
-      retries: 20, // 重试次数
-  };
-
-  let mut retry_count = 1;
-
-  loop {
-      let (request, abort_controller) = build_request(&config)?;
-
-      let start_time = Date::now();
-
-      // 创建超时 Promise
-      let timeout_promise = Promise::new(&mut |resolve, _reject| {
-          let window = window().unwrap();
-          let timeout_value = Object::new();
-          Reflect::set(&timeout_value, &"timeout".into(), &JsValue::TRUE).unwrap();
-          window
-              .set_timeout_with_callback_and_timeout_and_arguments_1(
-                  &resolve,
-                  config.timeout,
-                  &timeout_value,
-              )
-              .unwrap();
-      });
-
-      let fetch_promise = window().unwrap().fetch_with_request(&request);
-      let race_promise = Promise::race(&Array::of2(&fetch_promise, &timeout_promise));
-
-      match handle_response(
-          JsFuture::from(race_promise).await,
-          &start_time,
-          &abort_controller,
-          &config,
-          &retry_count,
-          failure_stats,
-          project_name.clone(),
-          author.clone(),
-      )
-      .await?
-      {
-          ResponseResult::Success(json_value) => return Ok(json_value),
-          ResponseResult::Retry => {
-              retry_count += 1;
-              if retry_count >= config.retries {
-                  return Err(JsValue::from_str("达到最大重试次数"));
-              }
-              // 在重试前等待 300ms
-              delay(300).await;
-              continue;
-          }
-      }
-  }
+// 按分页响应头依次获取所有页，最多获取 limit 条
+// 响应中没有分页响应头时，退化为按页码翻页，直到返回的数据不足一页
+// on_page 在每页获取后调用，参数为已获取的条数和数据总数
+async fn fetch_paginated<T: DeserializeOwned>(
+    ctx: &AnalysisContext,
+    first_url: String,
+    per_page: u32,
+    context: &RequestContext,
+    limit: Option<usize>,
+    mut on_page: impl FnMut(usize, Option<u32>),
+) -> Result<Paginated<T>, AnalysisError> {
+    let mut url = first_url;
+    let mut items = Vec::new();
+    let mut total = None;
+    let mut page_num = 1;
+    let mut last_body = String::new();
+
+    loop {
+        let response = fetch_with_retry(ctx, &url, context).await?;
+        // 服务端忽略分页参数时会重复返回同一页
+        if page_num > 1 && response.body == last_body {
+            break;
+        }
+        let page: Vec<T> = serde_json::from_str(&response.body)?;
+        let page_info = pagination::page_info(&response, &url, &ctx.config.gitlab_api);
+        let page_len = page.len();
+        total = page_info.total.or(total);
+        items.extend(page);
+
+        let next_url = if page_info.has_headers {
+            page_info.next_url
+        } else if page_len >= per_page as usize {
+            Some(pagination::set_query_param(&url, "page", &(page_num + 1).to_string()))
+        } else {
+            None
+        };
+
+        let reached_limit = limit.is_some_and(|limit| items.len() >= limit);
+        let next_url = next_url.filter(|_| page_len > 0 && !reached_limit);
+        // 已经取完所有页时，总数就是实际获取到的数量
+        if next_url.is_none() && total.is_none() && !reached_limit {
+            total = Some(items.len() as u32);
+        }
+        on_page(items.len(), total);
+
+        match next_url {
+            Some(next_url) => {
+                url = next_url;
+                page_num += 1;
+                last_body = response.body;
+            }
+            None => break,
+        }
+    }
+
+    if let Some(limit) = limit {
+        items.truncate(limit);
+    }
+
+    Ok(Paginated { items, total })
 }
 
 // 主函数入口
 #[wasm_bindgen]
 pub async fn analyze_gitlab_projects(config: JsValue) -> Result<JsValue, JsValue> {
+    let options = AnalysisOptions {
+        on_progress: js_progress_callback(&config)?,
+        cancel: js_cancel_token(&config)?,
+        on_checkpoint: js_checkpoint_callback(&config)?,
+        cache: js_response_cache(&config)?,
+    };
     let config: Config = serde_wasm_bindgen::from_value(config)?;
-    let failure_stats = Arc::new(Mutex::new(Vec::new()));
-    let author_stats = Arc::new(Mutex::new(HashMap::new()));
+    let report = analyze_with_transport(config, transport::default_transport(), options).await?;
+    // 报告中按键统计的数据以普通对象返回，而不是 Map
+    let serializer = serde_wasm_bindgen::Serializer::new().serialize_maps_as_objects(true);
+    Ok(report.serialize(&serializer)?)
+}
+
+// 从 js 配置中读取 on_progress 回调
+fn js_progress_callback(config: &JsValue) -> Result<Option<ProgressCallback>, JsValue> {
+    let value = js_sys::Reflect::get(config, &"on_progress".into())?;
+    if value.is_undefined() || value.is_null() {
+        return Ok(None);
+    }
+    let callback: js_sys::Function = value
+        .dyn_into()
+        .map_err(|_| JsValue::from_str("on_progress 必须是函数"))?;
+
+    Ok(Some(Box::new(move |event: &ProgressEvent| {
+        if let Ok(value) = serde_wasm_bindgen::to_value(event) {
+            if let Err(e) = callback.call1(&JsValue::NULL, &value) {
+                log_error(&format!("on_progress 回调执行出错: {:?}", e));
+            }
+        }
+    })))
+}
+
+// 从 js 配置中读取 on_checkpoint 回调，断点以普通对象传入，可以直接 JSON.stringify 保存
+fn js_checkpoint_callback(config: &JsValue) -> Result<Option<CheckpointCallback>, JsValue> {
+    let value = js_sys::Reflect::get(config, &"on_checkpoint".into())?;
+    if value.is_undefined() || value.is_null() {
+        return Ok(None);
+    }
+    let callback: js_sys::Function = value
+        .dyn_into()
+        .map_err(|_| JsValue::from_str("on_checkpoint 必须是函数"))?;
+
+    Ok(Some(Box::new(move |checkpoint: &Checkpoint| {
+        let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+        if let Ok(value) = checkpoint.serialize(&serializer) {
+            if let Err(e) = callback.call1(&JsValue::NULL, &value) {
+                log_error(&format!("on_checkpoint 回调执行出错: {:?}", e));
+            }
+        }
+    })))
+}
+
+// 从 js 配置中读取 cache
+// true 使用默认名称的 IndexedDB，字符串作为 IndexedDB 数据库名，对象需提供 get/set 方法
+fn js_response_cache(config: &JsValue) -> Result<Option<Rc<dyn ResponseCache>>, JsValue> {
+    let value = js_sys::Reflect::get(config, &"cache".into())?;
+    // 未配置或为 false 时不使用缓存
+    if value.is_falsy() {
+        return Ok(None);
+    }
+
+    let indexed_db = |name: String| -> Result<Option<Rc<dyn ResponseCache>>, JsValue> {
+        match cache::IndexedDbCache::new(name) {
+            Some(cache) => Ok(Some(Rc::new(cache))),
+            None => Err(JsValue::from_str(
+                "当前环境不支持 IndexedDB，请传入带有 get/set 方法的 cache 对象",
+            )),
+        }
+    };
+
+    if value.as_bool() == Some(true) {
+        return indexed_db(cache::DEFAULT_DB_NAME.to_string());
+    }
+    if let Some(name) = value.as_string() {
+        return indexed_db(name);
+    }
+    match cache::JsCache::new(value) {
+        Some(cache) => Ok(Some(Rc::new(cache))),
+        None => Err(JsValue::from_str("cache 对象必须提供 get 和 set 方法")),
+    }
+}
+
+// 从 js 配置中读取 signal（AbortSignal）
+fn js_cancel_token(config: &JsValue) -> Result<Option<CancelToken>, JsValue> {
+    let value = js_sys::Reflect::get(config, &"signal".into())?;
+    if value.is_undefined() || value.is_null() {
+        return Ok(None);
+    }
+    let signal: web_sys::AbortSignal = value
+        .dyn_into()
+        .map_err(|_| JsValue::from_str("signal 必须是 AbortSignal"))?;
 
-    // 获取项目列表
-    let projects = get_group_projects(&config, &failure_stats).await?;
-    console::log_1(&format!("[获取项目成功] 本次分析 {} 个项目", projects.len()).into());
+    Ok(Some(CancelToken::from_signal(signal)))
+}
+
+// 使用指定的传输层执行分析，可在非浏览器环境（如 Node、原生测试）中直接调用
+pub async fn analyze_with_transport(
+    mut config: Config,
+    transport: Rc<dyn Transport>,
+    options: AnalysisOptions,
+) -> Result<Report, AnalysisError> {
+    // 从断点恢复已处理的部分
+    let (checkpoint, author_stats) = match config.resume_from.take() {
+        Some(resume_from) => {
+            resume_from.validate(&config).map_err(AnalysisError::Config)?;
+            log("[断点续跑] 从断点恢复分析");
+            (
+                CheckpointState::from_checkpoint(&resume_from),
+                resume_from.into_author_stats(),
+            )
+        }
+        None => (CheckpointState::default(), HashMap::new()),
+    };
+
+    let limiter = Limiter::new(config.max_concurrent_requests as usize);
+    let mailmap = config.mailmap.as_deref().map(Mailmap::parse).unwrap_or_default();
+    let bots = match &config.bot_patterns {
+        Some(patterns) => BotDetector::new(patterns),
+        None => BotDetector::new(bots::DEFAULT_BOT_PATTERNS),
+    }
+    .map_err(AnalysisError::Config)?;
+    let path_filter =
+        PathFilter::new(&config.valid_extensions, &config.ignored_paths).map_err(AnalysisError::Config)?;
+    let ctx = AnalysisContext {
+        config,
+        transport,
+        author_stats: Mutex::new(author_stats),
+        failure_stats: Mutex::new(Vec::new()),
+        rate_limit: RateLimitState::default(),
+        limiter,
+        progress: ProgressTracker::new(options.on_progress),
+        cancel: options.cancel.unwrap_or_default(),
+        checkpoint,
+        on_checkpoint: options.on_checkpoint,
+        cache: options.cache,
+        mailmap,
+        bots,
+        path_filter,
+    };
+    let config = &ctx.config;
+    if config.fast_mode && !config.uses_commit_stats() {
+        log("[快速模式] 配置了 valid_extensions 或 ignored_paths，需要逐个下载提交差异");
+    }
+
+    // 获取项目列表，获取过程中被取消时返回空报告
+    let listing = match get_group_projects(&ctx).await {
+        Err(AnalysisError::Cancelled) => Paginated {
+            items: Vec::new(),
+            total: None,
+        },
+        result => result?,
+    };
+    let projects = &listing.items;
+    log(&format!(
+        "[获取项目成功] 群组共 {} 个项目，本次分析 {} 个项目",
+        listing
+            .total
+            .map(|total| total.to_string())
+            .unwrap_or_else(|| "未知".to_string()),
+        projects.len()
+    ));
 
     // 过滤排除的项目
     let filtered_projects: Vec<_> = projects
@@ -431,156 +1099,513 @@ pub async fn analyze_gitlab_projects(config: JsValue) -> Result<JsValue, JsValue
         .filter(|p| !config.excluded_projects.contains(&p.name))
         .collect();
 
-    // 处理每个项目
-    for chunk in filtered_projects.chunks(config.max_concurrent_requests as usize) {
-        let futures: Vec<_> = chunk
-            .iter()
-            .map(|project| {
-                let config = &config;
-                let author_stats = Arc::clone(&author_stats);
-                let failure_stats = Arc::clone(&failure_stats);
+    ctx.progress.set_total_projects(filtered_projects.len() as u32);
+    ctx.progress.emit(ProgressEvent::ProjectsDiscovered {
+        total: listing.total,
+        analyzed: filtered_projects.len() as u32,
+    });
 
-                async move { process_project(project, config, &author_stats, &failure_stats).await }
+    // 所有项目同时获取提交记录，实际并发的请求数由 ctx.limiter 控制
+    // 跨项目去重时，项目只需等待优先级更高的项目的提交列表，归属确定后立即开始处理
+    let listings: Vec<_> = filtered_projects
+        .iter()
+        .enumerate()
+        .map(|(index, project)| {
+            fetch_project_commits(&ctx, project, index as u32)
+                .map(Rc::new)
+                .shared()
+        })
+        .collect();
+    let dedup_projects: Vec<_> = filtered_projects
+        .iter()
+        .map(|project| DedupProject {
+            project_id: project.id,
+            forked_from: project.forked_from_project.as_ref().map(|f| f.id),
+        })
+        .collect();
+    let preferred = dedup::preferred_projects(&dedup_projects, config.dedup_policy);
+    let claimed = match preferred {
+        Some(_) => ctx.checkpoint.processed_shas(),
+        None => HashMap::new(),
+    };
+    let duplicates_dropped = Cell::new(0);
+
+    let futures: Vec<_> = filtered_projects
+        .iter()
+        .enumerate()
+        .map(|(index, project)| {
+            let (ctx, listings, claimed, duplicates_dropped) = (&ctx, &listings, &claimed, &duplicates_dropped);
+            let preferred = preferred.as_ref().map(|preferred| &preferred[index]);
+            async move {
+                let Some(mut commits) = Option::clone(&*listings[index].clone().await) else {
+                    return;
+                };
+                if let Some(preferred) = preferred {
+                    let preferred = join_all(preferred.iter().map(|&other| listings[other].clone())).await;
+                    let total = commits.len();
+                    commits = dedup_commits(project, commits, &preferred, claimed);
+                    duplicates_dropped.set(duplicates_dropped.get() + (total - commits.len()) as u32);
+                }
+                process_project(ctx, project, commits).await;
+            }
+        })
+        .collect();
+    join_all(futures).await;
+    let duplicates_dropped = duplicates_dropped.get();
+    if duplicates_dropped > 0 {
+        log(&format!("[提交去重] {} 个提交已在其他项目中统计", duplicates_dropped));
+    }
+
+    // 导出最终断点，取消后可以从这里继续
+    save_checkpoint(&ctx);
+
+    // 匹配 GitLab 账号，取消后跳过
+    let users = if config.resolve_users && !ctx.cancel.is_cancelled() {
+        let authors: Vec<_> = ctx
+            .author_stats
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(key, stat)| AuthorIdentity {
+                key: key.clone(),
+                name: stat.author_name.clone(),
+                email: stat.author_email.clone(),
             })
             .collect();
+        Some(users::resolve_users(&ctx, &authors).await)
+    } else {
+        None
+    };
 
-        join_all(futures).await;
+    // 生成报告，机器人不计入常规统计
+    // 合并提交数和排除行数是整体口径，在拆分机器人之前计算，不随 bot_policy 变化
+    let author_stats = std::mem::take(&mut *ctx.author_stats.lock().unwrap());
+    let merge_commits = author_stats.values().map(|a| a.total_merges).sum::<u32>() + ctx.checkpoint.excluded_merges();
+    let excluded_lines = author_stats
+        .values()
+        .flat_map(|a| a.projects.values())
+        .flat_map(|p| &p.excluded_lines)
+        .fold(BTreeMap::new(), |mut total, (&category, &lines)| {
+            *total.entry(category).or_default() += lines;
+            total
+        });
+    let (author_stats, bot_stats) = ctx.bots.partition(author_stats, users.as_ref());
+    let failure_stats = ctx.failure_stats.lock().unwrap();
+    let project_summary = ProjectSummary {
+        total: listing.total,
+        fetched: projects.len() as u32,
+        excluded: (projects.len() - filtered_projects.len()) as u32,
+        analyzed: filtered_projects.len() as u32,
+    };
+    let cancelled = ctx.cancel.is_cancelled();
+    if cancelled {
+        log("[分析已取消] 返回已完成部分的报告");
+        ctx.progress.emit(ProgressEvent::Cancelled);
     }
-
-    // 生成报告
-    let author_stats = author_stats.lock().unwrap();
-    let failure_stats = failure_stats.lock().unwrap();
-    let report = generate_report(&author_stats, &failure_stats, &config);
-    console::log_1(&format!("[生成报告成功！]").into());
-    Ok(serde_wasm_bindgen::to_value(&report)?)
+    let mut report = generate_report(
+        &author_stats,
+        &failure_stats,
+        project_summary,
+        users.as_ref(),
+        config,
+    );
+    report.cancelled = cancelled;
+    report.duplicates_dropped = duplicates_dropped;
+    report.merge_commits = merge_commits;
+    report.excluded_lines = excluded_lines;
+    if config.bot_policy == BotPolicy::Separate {
+        let mut bot_stats: Vec<_> = bot_stats
+            .iter()
+            .map(|(key, stat)| author_code_stat(key, stat, users.as_ref()))
+            .collect();
+        bot_stats.sort_by_key(|s| std::cmp::Reverse(s.size));
+        report.bot_stats = Some(bot_stats);
+    }
+    log("[生成报告成功！]");
+    Ok(report)
 }
 
-// 实现获取群组项目
-async fn get_group_projects(
-    config: &Config,
-    failure_stats: &Arc<Mutex<Vec<FailureRecord>>>,
-) -> Result<Vec<Project>, JsValue> {
-    console::log_1(&format!("开始获取项目...").into());
+// 实现获取群组项目，按分页响应头翻页，最多获取 projects_num 个项目
+async fn get_group_projects(ctx: &AnalysisContext) -> Result<Paginated<Project>, AnalysisError> {
+    log("开始获取项目...");
+    let config = &ctx.config;
+    let per_page = config.projects_num.clamp(1, pagination::MAX_PER_PAGE);
     let url = format!(
         "{}/groups/{}/projects?per_page={}&include_subgroups=true&order_by=last_activity_at&sort=desc",
-        config.gitlab_api, config.group_id, config.projects_num
+        config.gitlab_api, config.group_id, per_page
     );
 
-    let context = Object::new();
-    Reflect::set(&context, &"type".into(), &"projects".into())?;
-    let details = Object::new();
-    Reflect::set(&details, &"operation".into(), &"获取项目列表...".into())?;
-    Reflect::set(&context, &"details".into(), &details)?;
-
-    let response = fetch_with_retry(&url, &config.gitlab_token, &context, failure_stats).await?;
-    let projects: Vec<Project> = serde_wasm_bindgen::from_value(response)?;
+    let context = RequestContext {
+        kind: RequestKind::Projects,
+        operation: "获取项目列表...".to_string(),
+        project_name: None,
+        author: None,
+    };
 
-    Ok(projects)
+    fetch_paginated(
+        ctx,
+        url,
+        per_page,
+        &context,
+        Some(config.projects_num as usize),
+        |_, _| {},
+    )
+    .await
 }
 
-// 实现处理单个项目
-async fn process_project(
-    project: &Project,
-    config: &Config,
-    author_stats: &Arc<Mutex<HashMap<String, AuthorStats>>>,
-    failure_stats: &Arc<Mutex<Vec<FailureRecord>>>,
-) -> Result<(), JsValue> {
-    console::log_1(&format!("开始分析项目... {}", project.name).into());
-    let commits = get_project_commit_stats(
+// 开始分析项目并获取提交记录，返回 None 表示项目不需要继续处理（已取消、断点中已完成或获取失败）
+async fn fetch_project_commits(ctx: &AnalysisContext, project: &Project, index: u32) -> Option<Vec<Commit>> {
+    // 取消后不再开始新的项目
+    if ctx.cancel.is_cancelled() {
+        return None;
+    }
+    log(&format!("开始分析项目... {}", project.name));
+    ctx.progress.emit(ProgressEvent::ProjectStarted {
+        project: project.name.clone(),
+        index,
+        total: ctx.progress.total_projects(),
+    });
+
+    if ctx.checkpoint.is_project_processed(project.id) {
+        log(&format!("[断点续跑] 跳过已完成的项目 {}", project.name));
+        finish_project(ctx, project);
+        return None;
+    }
+
+    let config = &ctx.config;
+    match get_project_commit_stats(
+        ctx,
         project.id,
         &config.start_date,
         &config.end_date,
         &project.name,
-        config,
-        failure_stats,
     )
-    .await?;
+    .await
+    {
+        Ok(commits) => Some(commits.items),
+        Err(_) => {
+            finish_project(ctx, project);
+            None
+        }
+    }
+}
+
+// 按 Config.dedup_policy 去掉在其他项目中统计的重复提交，preferred 为优先级更高的项目的提交列表
+fn dedup_commits(
+    project: &Project,
+    commits: Vec<Commit>,
+    preferred: &[Rc<Option<Vec<Commit>>>],
+    claimed: &HashMap<String, i64>,
+) -> Vec<Commit> {
+    let preferred: HashSet<&str> = preferred
+        .iter()
+        .filter_map(|commits| commits.as_ref().as_ref())
+        .flatten()
+        .map(|commit| commit.id.as_str())
+        .collect();
+    commits
+        .into_iter()
+        .filter(|commit| dedup::is_owner(&commit.id, project.id, claimed, &preferred))
+        .collect()
+}
+
+// 处理单个项目的提交
+async fn process_project(ctx: &AnalysisContext, project: &Project, commits: Vec<Commit>) {
+    // 跳过断点中已处理的提交
+    let pending: Vec<_> = commits
+        .iter()
+        .filter(|commit| !ctx.checkpoint.is_commit_processed(project.id, &commit.id))
+        .collect();
 
-    for commit_batch in commits.chunks(config.max_concurrent_requests as usize) {
-        let mut futures = Vec::new();
+    // 没有需要处理的提交时不再读取 .mailmap、.gitattributes 和回滚关系
+    let (mailmap, attributes, reverts) = if pending.is_empty() {
+        (Mailmap::default(), GitAttributes::default(), Reverts::default())
+    } else {
+        let mailmap = project_mailmap(ctx, project).await;
+        let attributes = project_gitattributes(ctx, project).await;
+        // 在完整的提交列表中查找，断点恢复后仍能关联到回滚前已统计的提交
+        let reverts = revert::find_reverts(ctx, project, &commits).await;
+        if reverts.revert_count() > 0 {
+            log(&format!("[回滚提交] {} 发现 {} 个", project.name, reverts.revert_count()));
+        }
+        (mailmap, attributes, reverts)
+    };
+
+    let started_at = now_ms();
+    let total = pending.len() as u32;
+    let processed = Cell::new(0);
+    let failed = Cell::new(false);
+
+    let futures: Vec<_> = pending
+        .iter()
+        .map(|&commit| async {
+            let result = process_commit(ctx, commit, project, &mailmap, &reverts, &attributes).await;
+            match result {
+                // 取消后未处理的提交不计入进度
+                Err(AnalysisError::Cancelled) => return result,
+                Err(_) => failed.set(true),
+                Ok(_) => {}
+            }
+            processed.set(processed.get() + 1);
+            ctx.progress.emit(ProgressEvent::DiffsProcessed {
+                project: project.name.clone(),
+                processed: processed.get(),
+                total,
+                eta_ms: progress::eta(started_at, processed.get(), total),
+            });
+            result
+        })
+        .collect();
+    join_all(futures).await;
+    log(&format!("[分析项目{}完成]", project.name));
+
+    // 所有提交都处理成功时才标记项目完成，否则恢复时重新处理失败的提交
+    if !failed.get() && !ctx.cancel.is_cancelled() {
+        ctx.checkpoint.mark_project(project.id);
+        save_checkpoint(ctx);
+    }
+    finish_project(ctx, project);
+}
 
-        for commit in commit_batch {
-            futures.push(process_commit(
-                commit,
-                project,
-                config,
-                author_stats,
-                failure_stats,
-            ));
+// 项目使用的作者别名映射，项目中的 .mailmap 读取失败时只使用 Config.mailmap
+async fn project_mailmap(ctx: &AnalysisContext, project: &Project) -> Mailmap {
+    if !ctx.config.fetch_mailmap {
+        return ctx.mailmap.clone();
+    }
+    match get_repository_file(ctx, project, ".mailmap").await {
+        Ok(Some(text)) => Mailmap::parse(&text).merged(&ctx.mailmap),
+        Ok(None) => ctx.mailmap.clone(),
+        Err(e) => {
+            log_error(&format!("[读取 .mailmap 失败] {} {}", project.name, e));
+            ctx.mailmap.clone()
         }
+    }
+}
 
-        futures::future::join_all(futures).await;
+// 项目 .gitattributes 中的排除规则，读取失败时不排除
+async fn project_gitattributes(ctx: &AnalysisContext, project: &Project) -> GitAttributes {
+    if !ctx.config.fetch_gitattributes || ctx.config.uses_commit_stats() {
+        return GitAttributes::default();
+    }
+    match get_repository_file(ctx, project, ".gitattributes").await {
+        Ok(Some(text)) => GitAttributes::parse(&text),
+        Ok(None) => GitAttributes::default(),
+        Err(e) => {
+            log_error(&format!("[读取 .gitattributes 失败] {} {}", project.name, e));
+            GitAttributes::default()
+        }
     }
-    console::log_1(&format!("[分析项目{}完成]", project.name).into());
+}
 
-    Ok(())
+// 读取项目默认分支中的文件，文件不存在或仓库为空时返回 None
+async fn get_repository_file(
+    ctx: &AnalysisContext,
+    project: &Project,
+    file_path: &str,
+) -> Result<Option<String>, AnalysisError> {
+    let Some(branch) = &project.default_branch else {
+        return Ok(None);
+    };
+    let url = format!(
+        "{}/projects/{}/repository/files/{}/raw?ref={}",
+        ctx.config.gitlab_api,
+        project.id,
+        encode_uri_component(file_path),
+        encode_uri_component(branch)
+    );
+
+    let context = RequestContext {
+        kind: RequestKind::Files,
+        operation: format!("读取 {}", file_path),
+        project_name: Some(project.name.clone()),
+        author: None,
+    };
+
+    let response = fetch_with_retry(ctx, &url, &context).await?;
+    if response.status == 404 {
+        return Ok(None);
+    }
+    Ok(Some(response.body))
 }
 
-// 获取项目提交统计
+// 与 js 的 encodeURIComponent 一致
+fn encode_uri_component(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'!' | b'~' | b'*' | b'\'' | b'(' | b')' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+// 通过回调导出断点
+fn save_checkpoint(ctx: &AnalysisContext) {
+    if let Some(on_checkpoint) = &ctx.on_checkpoint {
+        let checkpoint = ctx
+            .checkpoint
+            .snapshot(&ctx.config, &ctx.author_stats.lock().unwrap());
+        on_checkpoint(&checkpoint);
+    }
+}
+
+// 标记项目分析完成
+fn finish_project(ctx: &AnalysisContext, project: &Project) {
+    let (finished, eta_ms) = ctx.progress.finish_project();
+    ctx.progress.emit(ProgressEvent::ProjectFinished {
+        project: project.name.clone(),
+        finished,
+        total: ctx.progress.total_projects(),
+        eta_ms,
+    });
+}
+
+// 获取项目提交统计，按分页响应头翻页
 async fn get_project_commit_stats(
+    ctx: &AnalysisContext,
     project_id: i64,
     since: &str,
     until: &str,
     project_name: &str,
-    config: &Config,
-    failure_stats: &Arc<Mutex<Vec<FailureRecord>>>,
-) -> Result<Vec<Commit>, JsValue> {
-    let mut all_commits = Vec::new();
-    let mut page = 1;
+) -> Result<Paginated<Commit>, AnalysisError> {
+    let mut url = format!(
+        "{}/projects/{}/repository/commits?since={}&until={}&per_page={}&all=true",
+        ctx.config.gitlab_api,
+        project_id,
+        since,
+        until,
+        pagination::MAX_PER_PAGE
+    );
+    if ctx.config.uses_commit_stats() {
+        url.push_str("&with_stats=true");
+    }
 
-    loop {
-        let url = format!(
-            "{}/projects/{}/repository/commits?since={}&until={}&per_page=100&page={}&all=true",
-            config.gitlab_api, project_id, since, until, page
-        );
-
-        let context = Object::new();
-        Reflect::set(&context, &"type".into(), &"commits".into())?;
-        let details = Object::new();
-        Reflect::set(&details, &"projectName".into(), &project_name.into())?;
-        Reflect::set(&details, &"operation".into(), &"获取提交记录".into())?;
-        Reflect::set(&context, &"details".into(), &details)?;
-
-        let response =
-            fetch_with_retry(&url, &config.gitlab_token, &context, failure_stats).await?;
-        let commits: Vec<Commit> = serde_wasm_bindgen::from_value(response)?;
-
-        if commits.is_empty() {
-            break;
-        }
+    let context = RequestContext {
+        kind: RequestKind::Commits,
+        operation: "获取提交记录".to_string(),
+        project_name: Some(project_name.to_string()),
+        author: None,
+    };
 
-        all_commits.extend(commits);
-        page += 1;
-    }
+    fetch_paginated(
+        ctx,
+        url,
+        pagination::MAX_PER_PAGE,
+        &context,
+        None,
+        |fetched, total| {
+            match total {
+                Some(total) => log(&format!("[获取提交记录] {} 已获取 {}/{} 条", project_name, fetched, total)),
+                None => log(&format!("[获取提交记录] {} 已获取 {} 条", project_name, fetched)),
+            }
+            ctx.progress.emit(ProgressEvent::CommitsFetched {
+                project: project_name.to_string(),
+                fetched: fetched as u32,
+                total,
+            });
+        },
+    )
+    .await
+}
 
-    Ok(all_commits)
+// 记录不进入作者统计的合并提交，到达间隔时导出断点
+fn exclude_merge(ctx: &AnalysisContext, project: &Project, commit: &Commit) {
+    let pending = ctx.checkpoint.exclude_merge(project.id, &commit.id);
+    let interval = ctx.config.checkpoint_interval;
+    if interval > 0 && pending >= interval {
+        save_checkpoint(ctx);
+    }
 }
 
 // 处理单个提交
 async fn process_commit(
+    ctx: &AnalysisContext,
     commit: &Commit,
     project: &Project,
-    config: &Config,
-    author_stats: &Arc<Mutex<HashMap<String, AuthorStats>>>,
-    failure_stats: &Arc<Mutex<Vec<FailureRecord>>>,
-) -> Result<(), JsValue> {
-    let stats = analyze_commit_diffs(
-        project.id,
-        &project.name,
-        &commit.id,
-        &commit.author_email,
-        config,
-        failure_stats,
-    )
-    .await?;
+    mailmap: &Mailmap,
+    reverts: &Reverts,
+    attributes: &GitAttributes,
+) -> Result<(), AnalysisError> {
+    let merge_policy = ctx.config.merge_policy;
+    let is_merge = commit.is_merge();
+    if is_merge && merge_policy == MergePolicy::Exclude {
+        exclude_merge(ctx, project, commit);
+        return Ok(());
+    }
 
-    let mut branch_info = get_commit_branches(
-        project.id,
-        &commit.id,
-        &project.name,
-        &commit.author_email,
-        config,
-        failure_stats,
+    let revert_policy = ctx.config.revert_policy;
+    let role = reverts.role(&commit.id);
+    let in_revert = role.is_revert || role.is_reverted;
+    // 被回滚的提交仍需获取差异，用于统计 revertedLines
+    if role.is_revert && !role.is_reverted && revert_policy == RevertPolicy::Exclude {
+        return Ok(());
+    }
+    let counts_lines = !in_revert || revert_policy == RevertPolicy::Count;
+
+    // 按规范身份归并作者，与主作者身份相同的共同作者只统计一次
+    // 不统计机器人时只去掉机器人身份，其余作者成为主作者；所有作者都是机器人时直接跳过，省去差异请求
+    let exclude_bots = ctx.config.bot_policy == BotPolicy::Exclude;
+    let mut authors: Vec<Identity> = Vec::new();
+    let identities = std::iter::once(mailmap.resolve(&commit.author_name, &commit.author_email)).chain(
+        coauthor::parse(&commit.message)
+            .into_iter()
+            .map(|coauthor| mailmap.resolve(&coauthor.name, &coauthor.email)),
+    );
+    for identity in identities {
+        if exclude_bots && ctx.bots.is_bot(&identity.name, &identity.email, None) {
+            continue;
+        }
+        if authors.iter().all(|author| author.key != identity.key) {
+            authors.push(identity);
+        }
+    }
+    if authors.is_empty() {
+        // 机器人的合并提交不进入作者统计，但仍计入 mergeCommits
+        if is_merge {
+            exclude_merge(ctx, project, commit);
+        }
+        return Ok(());
+    }
+    let co_authored = authors.len() > 1;
+
+    // 差异和分支信息同时请求，快速模式下直接使用提交列表中的增删行数
+    let (stats, mut branch_info) = try_join(
+        async {
+            if is_merge && merge_policy == MergePolicy::CountWithoutLines {
+                return Ok(Stats::default());
+            }
+            if !counts_lines && !role.is_reverted {
+                return Ok(Stats::default());
+            }
+            match (&commit.stats, ctx.config.uses_commit_stats()) {
+                (Some(stats), true) => Ok(Stats {
+                    additions: stats.additions,
+                    deletions: stats.deletions,
+                    lines: stats.additions + stats.deletions,
+                    ..Default::default()
+                }),
+                _ => {
+                    analyze_commit_diffs(
+                        ctx,
+                        project.id,
+                        &project.name,
+                        &commit.id,
+                        &commit.author_email,
+                        attributes,
+                    )
+                    .await
+                }
+            }
+        },
+        get_commit_branches(
+            ctx,
+            project.id,
+            &commit.id,
+            &project.name,
+            &commit.author_email,
+        ),
     )
     .await?;
 
@@ -593,116 +1618,202 @@ async fn process_commit(
         }
     }
 
+    let coauthor_policy = ctx.config.coauthor_policy;
+    if coauthor_policy == CoauthorPolicy::PrimaryOnly {
+        authors.truncate(1);
+    }
+    let parts = authors.len();
+
     // 获取锁并更新统计信息
-    let mut author_stats = author_stats.lock().unwrap();
-    let author_stat = author_stats
-        .entry(commit.author_name.clone())
-        .or_insert_with(|| AuthorStats {
-            author_name: commit.author_name.clone(),
-            author_email: commit.author_email.clone(),
-            projects: HashMap::new(),
-            total_commits: 0,
-            total_additions: 0,
-            total_deletions: 0,
-            total_lines: 0,
-            total_files: 0,
-            total_size: 0,
-            commit_details: Vec::new(),
-        });
+    // 提交详情、合并提交、文件统计和排除的行数属于提交本身，只记在主作者名下，避免项目层面的汇总重复计算
+    // 共同作者只分得作者统计中的提交数和代码行
+    let mut author_stats = ctx.author_stats.lock().unwrap();
+    for (index, identity) in authors.into_iter().enumerate() {
+        let is_primary = index == 0;
+        let split;
+        let credit = if coauthor_policy == CoauthorPolicy::EvenSplit && parts > 1 {
+            split = stats.share(parts, index);
+            &split
+        } else {
+            &stats
+        };
+
+        let author_stat = author_stats
+            .entry(identity.key)
+            .or_insert_with(|| AuthorStats {
+                author_name: identity.name,
+                author_email: identity.email,
+                ..Default::default()
+            });
+
+        let project_stats = author_stat
+            .projects
+            .entry(project.name.clone())
+            .or_default();
+
+        // 被回滚的代码行记在原作者名下
+        if role.is_reverted {
+            project_stats.reverted_lines += credit.lines;
+            author_stat.total_reverted_lines += credit.lines;
+        }
+
+        if in_revert && revert_policy == RevertPolicy::Exclude {
+            continue;
+        }
+
+        project_stats.commits += 1;
+        author_stat.total_commits += 1;
+        if co_authored {
+            project_stats.co_authored += 1;
+            author_stat.total_co_authored += 1;
+        }
+
+        // 合并提交的代码行单独记录，不计入作者的代码量
+        if is_merge {
+            if is_primary {
+                project_stats.merges += 1;
+                author_stat.total_merges += 1;
+                if counts_lines {
+                    project_stats.merge_lines += stats.lines;
+                    author_stat.total_merge_lines += stats.lines;
+                }
+            }
+        } else if counts_lines {
+            record_stats(author_stat, &project.name, credit);
+            if is_primary {
+                record_files(author_stat, &project.name, &stats);
+            }
+        }
+
+        // 添加提交详情
+        if is_primary {
+            author_stat.commit_details.push(CommitDetail {
+                project: project.name.clone(),
+                branch: branch_info.branches.clone(),
+                tag: branch_info.tags.clone(),
+                message: commit.message.clone(),
+                committed_date: commit.committed_date.clone(),
+            });
+        }
+    }
+
+    // 与统计数据在同一把锁内记录，保证断点中的统计和已处理提交一致
+    let pending = ctx.checkpoint.mark_commit(project.id, &commit.id);
+    drop(author_stats);
 
+    let interval = ctx.config.checkpoint_interval;
+    if interval > 0 && pending >= interval {
+        save_checkpoint(ctx);
+    }
+
+    Ok(())
+}
+
+// 把提交的代码行累加到作者的项目统计和总计中
+fn record_stats(author_stat: &mut AuthorStats, project_name: &str, stats: &Stats) {
     let project_stats = author_stat
         .projects
-        .entry(project.name.clone())
-        .or_insert_with(ProjectStats::default);
+        .entry(project_name.to_string())
+        .or_default();
 
     // 更新项目统计
-    project_stats.commits += 1;
     project_stats.additions += stats.additions;
     project_stats.deletions += stats.deletions;
     project_stats.lines += stats.lines;
     project_stats.files += stats.files;
     project_stats.size += stats.size;
+    project_stats.hunks += stats.hunks;
 
     // 更新总计
-    author_stat.total_commits += 1;
     author_stat.total_additions += stats.additions;
     author_stat.total_deletions += stats.deletions;
     author_stat.total_lines += stats.lines;
     author_stat.total_files += stats.files;
     author_stat.total_size += stats.size;
+    author_stat.total_hunks += stats.hunks;
+}
 
-    // 添加提交详情
-    author_stat.commit_details.push(CommitDetail {
-        project: project.name.clone(),
-        branch: branch_info.branches,
-        tag: branch_info.tags,
-        message: commit.message.clone(),
-        committed_date: commit.committed_date.clone(),
-    });
-
-    Ok(())
+// 把提交的文件变更和排除的行数累加到作者的项目统计中，每个提交只记录一次
+fn record_files(author_stat: &mut AuthorStats, project_name: &str, stats: &Stats) {
+    let project_stats = author_stat
+        .projects
+        .entry(project_name.to_string())
+        .or_default();
+
+    for change in &stats.file_changes {
+        project_stats
+            .file_stats
+            .entry(change.path.clone())
+            .or_default()
+            .record(change);
+    }
+    for (&category, &lines) in &stats.excluded_lines {
+        *project_stats.excluded_lines.entry(category).or_default() += lines;
+    }
 }
 
 // 分析提交差异
 async fn analyze_commit_diffs(
+    ctx: &AnalysisContext,
     project_id: i64,
     project_name: &str,
     commit_sha: &str,
     author_email: &str,
-    config: &Config,
-    failure_stats: &Arc<Mutex<Vec<FailureRecord>>>,
-) -> Result<Stats, JsValue> {
+    attributes: &GitAttributes,
+) -> Result<Stats, AnalysisError> {
+    let config = &ctx.config;
     let url = format!(
         "{}/projects/{}/repository/commits/{}/diff",
         config.gitlab_api, project_id, commit_sha
     );
 
-    let context = Object::new();
-    Reflect::set(&context, &"type".into(), &"diffs".into())?;
-    let details = Object::new();
-    Reflect::set(&details, &"projectName".into(), &project_name.into())?;
-    Reflect::set(&details, &"authorEmail".into(), &author_email.into())?;
-    Reflect::set(&details, &"operation".into(), &"获取提交差异".into())?;
-    Reflect::set(&context, &"details".into(), &details)?;
+    let context = RequestContext {
+        kind: RequestKind::Diffs,
+        operation: "获取提交差异".to_string(),
+        project_name: Some(project_name.to_string()),
+        author: Some(author_email.to_string()),
+    };
 
-    let response = fetch_with_retry(&url, &config.gitlab_token, &context, failure_stats).await?;
-    let diffs: Vec<DiffInfo> = serde_wasm_bindgen::from_value(response)?;
+    let cache_key = cache::cache_key(CacheKind::Diff, &config.gitlab_api, project_id, commit_sha);
+    let diffs: Vec<DiffInfo> = fetch_json_cached(ctx, &url, &cache_key, &context).await?;
 
     let mut stats = Stats::default();
 
     for diff in diffs {
+        let status = FileStatus::from_flags(diff.new_file, diff.renamed_file, diff.deleted_file);
+        let old_path = diff.old_path.clone().filter(|_| status == FileStatus::Renamed);
         let file_path = diff.new_path.unwrap_or(diff.old_path.unwrap_or_default());
-        let ext = format!(".{}", file_path.split('.').last().unwrap_or(""));
 
-        // 检查是否应该忽略此文件
-        if config
-            .ignored_paths
-            .iter()
-            .any(|path| file_path.contains(path))
-        {
+        // 按 ignored_paths 和 valid_extensions 过滤
+        if !ctx.path_filter.is_included(&file_path) {
             continue;
         }
 
-        // 检查文件扩展名是否在允许列表中
-        if !config.valid_extensions.contains(&ext) {
+        let file_stats = diff.diff.as_deref().map(diff::parse).unwrap_or_default();
+
+        // 生成文件只记录排除的行数，项目 .gitattributes 中的标记优先
+        if let Some(category) = attributes.classify(&file_path).or_else(|| {
+            config
+                .generated_detection
+                .classify(&file_path, diff.diff.as_deref())
+        }) {
+            *stats.excluded_lines.entry(category).or_default() += file_stats.additions + file_stats.deletions;
             continue;
         }
 
         stats.files += 1;
-
-        if let Some(diff_content) = diff.diff {
-            let lines = diff_content.lines();
-            for line in lines {
-                if line.starts_with('+') && !line.starts_with("+++") {
-                    stats.additions += 1;
-                } else if line.starts_with('-') && !line.starts_with("---") {
-                    stats.deletions += 1;
-                }
-            }
-
-            stats.lines = stats.additions + stats.deletions;
-            stats.size += diff_content.len() as u64;
-        }
+        stats.additions += file_stats.additions;
+        stats.deletions += file_stats.deletions;
+        stats.hunks += file_stats.hunks;
+        stats.lines = stats.additions + stats.deletions;
+        stats.size += diff.diff.as_ref().map_or(0, |d| d.len() as u64);
+        stats.file_changes.push(FileChange {
+            path: file_path,
+            old_path,
+            status,
+            additions: file_stats.additions,
+            deletions: file_stats.deletions,
+        });
     }
 
     Ok(stats)
@@ -710,32 +1821,26 @@ async fn analyze_commit_diffs(
 
 // 获取提交所属的分支
 async fn get_commit_branches(
+    ctx: &AnalysisContext,
     project_id: i64,
     commit_sha: &str,
     project_name: &str,
     author_email: &str,
-    config: &Config,
-    failure_stats: &Arc<Mutex<Vec<FailureRecord>>>,
-) -> Result<BranchInfo, JsValue> {
+) -> Result<BranchInfo, AnalysisError> {
     let url = format!(
         "{}/projects/{}/repository/commits/{}/refs",
-        config.gitlab_api, project_id, commit_sha
+        ctx.config.gitlab_api, project_id, commit_sha
     );
 
-    let context = Object::new();
-    Reflect::set(&context, &"type".into(), &"refs".into())?;
-    let details = Object::new();
-    Reflect::set(&details, &"projectName".into(), &project_name.into())?;
-    Reflect::set(&details, &"authorEmail".into(), &author_email.into())?;
-    Reflect::set(
-        &details,
-        &"operation".into(),
-        &"获取提交对应的分支信息".into(),
-    )?;
-    Reflect::set(&context, &"details".into(), &details)?;
-
-    let response = fetch_with_retry(&url, &config.gitlab_token, &context, failure_stats).await?;
-    let refs: Vec<RefInfo> = serde_wasm_bindgen::from_value(response)?;
+    let context = RequestContext {
+        kind: RequestKind::Refs,
+        operation: "获取提交对应的分支信息".to_string(),
+        project_name: Some(project_name.to_string()),
+        author: Some(author_email.to_string()),
+    };
+
+    // 新建分支、打标签后同一提交的 refs 会变化，不走缓存
+    let refs: Vec<RefInfo> = fetch_json(ctx, &url, &context).await?;
 
     let branch = refs
         .iter()
@@ -755,56 +1860,80 @@ async fn get_commit_branches(
     })
 }
 
+// 生成作者的总计数据，各项目的数据放在 children 中
+fn author_code_stat(
+    author_key: &str,
+    author_stat: &AuthorStats,
+    users: Option<&HashMap<String, GitlabUser>>,
+) -> CodeStat {
+    let author_name = &author_stat.author_name;
+    // 生成总计数据
+    let mut total_stat = CodeStat {
+        key: format!("{}-total", author_key),
+        author: format!("【{}】", author_name),
+        email: author_stat.author_email.clone(),
+        project: "【总计】".to_string(),
+        commits: author_stat.total_commits,
+        additions: author_stat.total_additions,
+        deletions: author_stat.total_deletions,
+        lines: author_stat.total_lines,
+        files: author_stat.total_files,
+        size: (author_stat.total_size as f64 / 1024.0).round() as u64,
+        hunks: author_stat.total_hunks,
+        merges: author_stat.total_merges,
+        merge_lines: author_stat.total_merge_lines,
+        reverted_lines: author_stat.total_reverted_lines,
+        co_authored: author_stat.total_co_authored,
+        gitlab_user: users.and_then(|users| users.get(author_key)).cloned(),
+        is_total: Some(true),
+        children: Some(Vec::new()),
+    };
+
+    // 生成各个项目详细数据
+    let mut project_stats = Vec::new();
+    for (project_name, stats) in &author_stat.projects {
+        project_stats.push(CodeStat {
+            key: format!("{}-{}", author_key, project_name),
+            author: author_name.clone(),
+            email: author_stat.author_email.clone(),
+            project: project_name.clone(),
+            commits: stats.commits,
+            additions: stats.additions,
+            deletions: stats.deletions,
+            lines: stats.lines,
+            files: stats.files,
+            size: (stats.size as f64 / 1024.0).round() as u64,
+            hunks: stats.hunks,
+            merges: stats.merges,
+            merge_lines: stats.merge_lines,
+            reverted_lines: stats.reverted_lines,
+            co_authored: stats.co_authored,
+            gitlab_user: None,
+            is_total: None,
+            children: None,
+        });
+    }
+
+    // 对项目详情按代码量排序
+    project_stats.sort_by_key(|s| std::cmp::Reverse(s.size));
+
+    total_stat.children = Some(project_stats);
+    total_stat
+}
+
 fn generate_report(
     author_stats: &HashMap<String, AuthorStats>,
-    failure_stats: &Vec<FailureRecord>,
-    _config: &Config,
+    failure_stats: &[FailureRecord],
+    project_summary: ProjectSummary,
+    users: Option<&HashMap<String, GitlabUser>>,
+    config: &Config,
 ) -> Report {
     let mut code_stats = Vec::new();
     let mut commit_stats = Vec::new();
 
     // 先收集所有作者的统计数据
-    for (author_name, author_stat) in author_stats {
-        // 生成总计数据
-        let mut total_stat = CodeStat {
-            key: format!("{}-total", author_name),
-            author: format!("【{}】", author_name),
-            email: author_stat.author_email.clone(),
-            project: "【总计】".to_string(),
-            commits: author_stat.total_commits,
-            additions: author_stat.total_additions,
-            deletions: author_stat.total_deletions,
-            lines: author_stat.total_lines,
-            files: author_stat.total_files,
-            size: (author_stat.total_size as f64 / 1024.0).round() as u64,
-            is_total: Some(true),
-            children: Some(Vec::new()),
-        };
-
-        // 生成各个项目详细数据
-        let mut project_stats = Vec::new();
-        for (project_name, stats) in &author_stat.projects {
-            project_stats.push(CodeStat {
-                key: format!("{}-{}", author_name, project_name),
-                author: author_name.clone(),
-                email: author_stat.author_email.clone(),
-                project: project_name.clone(),
-                commits: stats.commits,
-                additions: stats.additions,
-                deletions: stats.deletions,
-                lines: stats.lines,
-                files: stats.files,
-                size: (stats.size as f64 / 1024.0).round() as u64,
-                is_total: None,
-                children: None,
-            });
-        }
-
-        // 对项目详情按代码量排序
-        project_stats.sort_by(|a, b| b.size.cmp(&a.size));
-
-        total_stat.children = Some(project_stats);
-        code_stats.push(total_stat);
+    for (author_key, author_stat) in author_stats {
+        code_stats.push(author_code_stat(author_key, author_stat, users));
 
         // 添加提交统计
         for detail in &author_stat.commit_details {
@@ -816,20 +1945,42 @@ fn generate_report(
                 tag: detail.tag.clone(),
                 committed_date: detail.committed_date.clone(),
                 message: detail.message.clone(),
+                conventional: conventional::parse(&detail.message),
             });
         }
     }
 
     // 对总计数据按代码量排序
-    code_stats.sort_by(|a, b| b.size.cmp(&a.size));
+    code_stats.sort_by_key(|s| std::cmp::Reverse(s.size));
 
     Report {
         code_stats,
+        bot_stats: None,
         commit_stats,
+        project_summary,
         failure_stats: if !failure_stats.is_empty() {
-            Some(failure_stats.clone())
+            Some(failure_stats.to_vec())
         } else {
             None
         },
+        file_stats: file_stats::build_report(author_stats, config.file_stats_top),
+        commit_types: conventional::build_report(author_stats),
+        duplicates_dropped: 0,
+        merge_commits: 0,
+        excluded_lines: BTreeMap::new(),
+        ownership: ownership::build_report(author_stats, config.ownership_depth),
+        unmatched_authors: users.map(|users| {
+            let mut unmatched: Vec<_> = author_stats
+                .iter()
+                .filter(|(key, _)| !users.contains_key(*key))
+                .map(|(_, stat)| UnmatchedAuthor {
+                    author: stat.author_name.clone(),
+                    email: stat.author_email.clone(),
+                })
+                .collect();
+            unmatched.sort_by(|a, b| a.author.cmp(&b.author));
+            unmatched
+        }),
+        cancelled: false,
     }
 }
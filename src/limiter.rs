@@ -0,0 +1,185 @@
+// 全局并发限制
+// 所有项目、提交、差异和分支请求共用同一组许可，同时进行中的 HTTP 请求数不超过 max_concurrent_requests
+// 请求完成后立即把许可交给排队最久的请求，不需要等待同一批的其他请求
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+pub struct Limiter {
+    inner: RefCell<Inner>,
+}
+
+struct Inner {
+    available: usize,
+    next_id: u64,
+    // 按排队顺序记录等待中的请求
+    waiters: VecDeque<(u64, Waker)>,
+}
+
+impl Inner {
+    // 有空闲许可时唤醒队首
+    fn wake_front(&self) {
+        if self.available > 0 {
+            if let Some((_, waker)) = self.waiters.front() {
+                waker.wake_by_ref();
+            }
+        }
+    }
+}
+
+impl Limiter {
+    pub fn new(permits: usize) -> Self {
+        Limiter {
+            inner: RefCell::new(Inner {
+                available: permits.max(1),
+                next_id: 0,
+                waiters: VecDeque::new(),
+            }),
+        }
+    }
+
+    // 等待一个许可，许可在 Permit 释放时归还
+    pub fn acquire(&self) -> Acquire<'_> {
+        Acquire {
+            limiter: self,
+            id: None,
+        }
+    }
+}
+
+pub struct Acquire<'a> {
+    limiter: &'a Limiter,
+    // 进入等待队列后的编号
+    id: Option<u64>,
+}
+
+impl<'a> Future for Acquire<'a> {
+    type Output = Permit<'a>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let limiter = self.limiter;
+        let mut inner = limiter.inner.borrow_mut();
+        let position = self
+            .id
+            .and_then(|id| inner.waiters.iter().position(|(waiter, _)| *waiter == id));
+
+        // 队列为空或者排在队首时才能拿到许可，保证先到先得
+        let is_front = match position {
+            Some(index) => index == 0,
+            None => inner.waiters.is_empty(),
+        };
+        if inner.available > 0 && is_front {
+            inner.available -= 1;
+            if position.is_some() {
+                inner.waiters.pop_front();
+            }
+            inner.wake_front();
+            drop(inner);
+            self.id = None;
+            return Poll::Ready(Permit { limiter });
+        }
+
+        match position {
+            Some(index) => inner.waiters[index].1 = cx.waker().clone(),
+            None => {
+                let id = inner.next_id;
+                inner.next_id += 1;
+                inner.waiters.push_back((id, cx.waker().clone()));
+                drop(inner);
+                self.id = Some(id);
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Acquire<'_> {
+    // 等待中被丢弃（例如请求被取消）时退出队列，并把唤醒传给下一个
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            let mut inner = self.limiter.inner.borrow_mut();
+            inner.waiters.retain(|(waiter, _)| *waiter != id);
+            inner.wake_front();
+        }
+    }
+}
+
+// 持有期间占用一个许可
+pub struct Permit<'a> {
+    limiter: &'a Limiter,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        let mut inner = self.limiter.inner.borrow_mut();
+        inner.available += 1;
+        inner.wake_front();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::task::noop_waker;
+    use futures::FutureExt;
+
+    fn poll<'a>(acquire: &mut Acquire<'a>) -> Poll<Permit<'a>> {
+        let waker = noop_waker();
+        acquire.poll_unpin(&mut Context::from_waker(&waker))
+    }
+
+    #[test]
+    fn bounds_permits_and_releases_on_drop() {
+        let limiter = Limiter::new(2);
+        let first = limiter.acquire().now_or_never().expect("应当立即拿到许可");
+        let _second = limiter.acquire().now_or_never().expect("应当立即拿到许可");
+
+        let mut third = limiter.acquire();
+        assert!(poll(&mut third).is_pending());
+        drop(first);
+        assert!(poll(&mut third).is_ready());
+    }
+
+    #[test]
+    fn zero_permits_still_allows_one_request() {
+        let limiter = Limiter::new(0);
+        let permit = limiter.acquire().now_or_never();
+        assert!(permit.is_some());
+        assert!(limiter.acquire().now_or_never().is_none());
+    }
+
+    #[test]
+    fn waiters_are_served_in_order() {
+        let limiter = Limiter::new(1);
+        let permit = limiter.acquire().now_or_never().unwrap();
+        let mut first = limiter.acquire();
+        let mut second = limiter.acquire();
+        assert!(poll(&mut first).is_pending());
+        assert!(poll(&mut second).is_pending());
+
+        drop(permit);
+        // 后排队的请求不能插队
+        assert!(poll(&mut second).is_pending());
+        let permit = poll(&mut first);
+        assert!(permit.is_ready());
+        drop(permit);
+        assert!(poll(&mut second).is_ready());
+    }
+
+    #[test]
+    fn dropped_waiter_leaves_the_queue() {
+        let limiter = Limiter::new(1);
+        let permit = limiter.acquire().now_or_never().unwrap();
+        let mut first = limiter.acquire();
+        let mut second = limiter.acquire();
+        assert!(poll(&mut first).is_pending());
+        assert!(poll(&mut second).is_pending());
+
+        drop(first);
+        drop(permit);
+        assert!(poll(&mut second).is_ready());
+    }
+}
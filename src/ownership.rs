@@ -0,0 +1,252 @@
+// 目录级别的代码归属
+// 基于文件统计，把每个文件的变更行数累加到各级目录上，得到每个项目的目录树以及每个目录的主要贡献者
+
+use crate::AuthorStats;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+// 默认统计的目录层级
+pub const DEFAULT_OWNERSHIP_DEPTH: u32 = 2;
+
+// 每个目录保留的贡献者数量
+const TOP_CONTRIBUTORS: usize = 3;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProjectOwnership {
+    project: String,
+    root: OwnershipNode,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OwnershipNode {
+    // 目录路径，根目录为空字符串
+    path: String,
+    // 该目录下（包括子目录）的变更行数
+    lines: u32,
+    contributors: Vec<Contributor>,
+    children: Vec<OwnershipNode>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Contributor {
+    author: String,
+    email: String,
+    lines: u32,
+    // 占该目录变更行数的比例，0 ~ 1
+    share: f64,
+}
+
+// 构建目录树时的中间结构
+#[derive(Default)]
+struct DirBuilder<'a> {
+    lines: u32,
+    // key 为作者统计的 key，没有邮箱的作者按名字区分；value 为 (作者, 邮箱, 行数)
+    authors: HashMap<&'a str, (&'a str, &'a str, u32)>,
+    children: BTreeMap<&'a str, DirBuilder<'a>>,
+}
+
+impl<'a> DirBuilder<'a> {
+    fn add(&mut self, dirs: &[&'a str], key: &'a str, author: &'a str, email: &'a str, lines: u32) {
+        self.lines += lines;
+        self.authors.entry(key).or_insert((author, email, 0)).2 += lines;
+        if let Some((dir, rest)) = dirs.split_first() {
+            self.children.entry(dir).or_default().add(rest, key, author, email, lines);
+        }
+    }
+
+    fn build(self, path: String) -> OwnershipNode {
+        let mut contributors: Vec<_> = self
+            .authors
+            .into_iter()
+            .filter(|(_, (_, _, lines))| *lines > 0)
+            .map(|(_, (author, email, lines))| Contributor {
+                author: author.to_string(),
+                email: email.to_string(),
+                lines,
+                share: share(lines, self.lines),
+            })
+            .collect();
+        contributors.sort_by(|a, b| {
+            b.lines
+                .cmp(&a.lines)
+                .then_with(|| a.author.cmp(&b.author))
+                .then_with(|| a.email.cmp(&b.email))
+        });
+        contributors.truncate(TOP_CONTRIBUTORS);
+
+        let mut children: Vec<_> = self
+            .children
+            .into_iter()
+            .map(|(dir, child)| {
+                let child_path = if path.is_empty() {
+                    dir.to_string()
+                } else {
+                    format!("{}/{}", path, dir)
+                };
+                child.build(child_path)
+            })
+            .collect();
+        children.sort_by_key(|c| std::cmp::Reverse(c.lines));
+
+        OwnershipNode {
+            path,
+            lines: self.lines,
+            contributors,
+            children,
+        }
+    }
+}
+
+// 保留 4 位小数
+fn share(lines: u32, total: u32) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    (lines as f64 / total as f64 * 10000.0).round() / 10000.0
+}
+
+// 生成每个项目的目录归属树，depth 为 0 时不统计
+pub fn build_report(author_stats: &HashMap<String, AuthorStats>, depth: u32) -> Vec<ProjectOwnership> {
+    if depth == 0 {
+        return Vec::new();
+    }
+
+    let mut projects: HashMap<&str, DirBuilder> = HashMap::new();
+    for (key, author_stat) in author_stats {
+        for (project_name, project_stats) in &author_stat.projects {
+            for (path, stat) in &project_stats.file_stats {
+                // 只取目录部分，最多 depth 层
+                let mut dirs: Vec<&str> = path.split('/').collect();
+                dirs.pop();
+                dirs.truncate(depth as usize);
+                projects.entry(project_name).or_default().add(
+                    &dirs,
+                    key,
+                    &author_stat.author_name,
+                    &author_stat.author_email,
+                    stat.lines(),
+                );
+            }
+        }
+    }
+
+    let mut ownership: Vec<_> = projects
+        .into_iter()
+        .map(|(project, root)| ProjectOwnership {
+            project: project.to_string(),
+            root: root.build(String::new()),
+        })
+        .collect();
+    ownership.sort_by_key(|p| std::cmp::Reverse(p.root.lines));
+    ownership
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_stats::{FileChange, FileStat, FileStatus};
+
+    // (项目, 路径, 行数)
+    type Files<'a> = &'a [(&'a str, &'a str, u32)];
+
+    // (作者 key, 名字, 邮箱, 文件)
+    fn author_stats(authors: &[(&str, &str, &str, Files)]) -> HashMap<String, AuthorStats> {
+        authors
+            .iter()
+            .map(|(key, name, email, files)| {
+                let mut author_stat = AuthorStats {
+                    author_name: name.to_string(),
+                    author_email: email.to_string(),
+                    ..Default::default()
+                };
+                for (project, path, lines) in files.iter() {
+                    let mut stat = FileStat::default();
+                    stat.record(&FileChange {
+                        path: path.to_string(),
+                        old_path: None,
+                        status: FileStatus::Modified,
+                        additions: *lines,
+                        deletions: 0,
+                    });
+                    author_stat
+                        .projects
+                        .entry(project.to_string())
+                        .or_default()
+                        .file_stats
+                        .insert(path.to_string(), stat);
+                }
+                (key.to_string(), author_stat)
+            })
+            .collect()
+    }
+
+    fn child<'a>(node: &'a OwnershipNode, path: &str) -> &'a OwnershipNode {
+        node.children
+            .iter()
+            .find(|child| child.path == path)
+            .unwrap_or_else(|| panic!("没有目录 {}", path))
+    }
+
+    #[test]
+    fn depth_zero_disables_the_report() {
+        let stats = author_stats(&[("a@x.com", "A", "a@x.com", &[("web", "src/a.ts", 1)])]);
+        assert!(build_report(&stats, 0).is_empty());
+    }
+
+    #[test]
+    fn truncates_directories_to_depth() {
+        let stats = author_stats(&[(
+            "a@x.com",
+            "A",
+            "a@x.com",
+            &[("web", "src/app/pages/home.ts", 5), ("web", "src/app/main.ts", 3), ("web", "README.md", 2)],
+        )]);
+        let report = build_report(&stats, 2);
+        let root = &report[0].root;
+        assert_eq!((report[0].project.as_str(), root.path.as_str(), root.lines), ("web", "", 10));
+        // 根目录下的文件只计入根节点
+        assert_eq!(root.children.len(), 1);
+        let src = child(root, "src");
+        assert_eq!(src.lines, 8);
+        let app = child(src, "src/app");
+        assert_eq!(app.lines, 8);
+        assert!(app.children.is_empty());
+    }
+
+    #[test]
+    fn keeps_top_contributors_with_rounded_share() {
+        let stats = author_stats(&[
+            ("a@x.com", "A", "a@x.com", &[("web", "src/a.ts", 1)]),
+            ("b@x.com", "B", "b@x.com", &[("web", "src/b.ts", 1)]),
+            ("c@x.com", "C", "c@x.com", &[("web", "src/c.ts", 1)]),
+            ("d@x.com", "D", "d@x.com", &[("web", "src/d.ts", 3)]),
+        ]);
+        let report = build_report(&stats, 1);
+        let src = child(&report[0].root, "src");
+        let contributors: Vec<_> = src
+            .contributors
+            .iter()
+            .map(|c| (c.author.as_str(), c.lines, c.share))
+            .collect();
+        assert_eq!(contributors.len(), TOP_CONTRIBUTORS);
+        assert_eq!(contributors, vec![("D", 3, 0.5), ("A", 1, 0.1667), ("B", 1, 0.1667)]);
+    }
+
+    #[test]
+    fn authors_without_email_stay_separate() {
+        let stats = author_stats(&[
+            ("alice", "Alice", "", &[("web", "src/a.ts", 2)]),
+            ("bob", "Bob", "", &[("web", "src/b.ts", 1)]),
+        ]);
+        let report = build_report(&stats, 1);
+        let authors: Vec<_> = report[0].root.contributors.iter().map(|c| c.author.as_str()).collect();
+        assert_eq!(authors, vec!["Alice", "Bob"]);
+    }
+
+    #[test]
+    fn projects_are_sorted_by_lines() {
+        let stats = author_stats(&[("a@x.com", "A", "a@x.com", &[("small", "a.ts", 1), ("large", "b.ts", 9)])]);
+        let projects: Vec<_> = build_report(&stats, 1).into_iter().map(|p| p.project).collect();
+        assert_eq!(projects, vec!["large", "small"]);
+    }
+}
@@ -0,0 +1,148 @@
+// GitLab 分页相关的辅助逻辑
+// 优先使用 Link 响应头中的 rel="next"（同时兼容 offset 分页和 keyset 分页），其次使用 X-Next-Page
+// GitLab 单页最多返回 100 条数据
+
+use crate::transport::HttpResponse;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+// GitLab 允许的最大 per_page
+pub const MAX_PER_PAGE: u32 = 100;
+
+static LINK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"<([^>]+)>\s*;\s*rel="([^"]+)""#).unwrap());
+
+// 从响应头中解析出的分页信息
+#[derive(Debug, Default)]
+pub struct PageInfo {
+    // 下一页的完整 URL，没有下一页时为 None
+    pub next_url: Option<String>,
+    // 数据总数，数据量过大时 GitLab 不返回该响应头
+    pub total: Option<u32>,
+    // 响应中是否带有分页响应头，部分代理会去掉这些响应头
+    pub has_headers: bool,
+}
+
+// 解析分页信息，api_base 用于把 Link 中的绝对地址替换成配置的地址（例如经过代理时）
+pub fn page_info(response: &HttpResponse, current_url: &str, api_base: &str) -> PageInfo {
+    let next_url = response
+        .header("Link")
+        .and_then(|link| {
+            LINK_RE
+                .captures_iter(link)
+                .find(|c| &c[2] == "next")
+                .map(|c| rebase_url(&c[1], api_base))
+        })
+        .or_else(|| {
+            response
+                .header("X-Next-Page")
+                .map(str::trim)
+                .filter(|page| !page.is_empty())
+                .map(|page| set_query_param(current_url, "page", page))
+        })
+        // 服务端忽略分页参数时会返回相同的地址，避免死循环
+        .filter(|next| next != current_url);
+
+    let total = response
+        .header("X-Total")
+        .and_then(|v| v.trim().parse::<u32>().ok());
+
+    let has_headers = response.header("Link").is_some() || response.header("X-Next-Page").is_some();
+
+    PageInfo {
+        next_url,
+        total,
+        has_headers,
+    }
+}
+
+// 把 GitLab 返回的地址中 /api/v4 之前的部分替换成配置的 api 地址
+fn rebase_url(url: &str, api_base: &str) -> String {
+    const API_PATH: &str = "/api/v4";
+    match url.find(API_PATH) {
+        Some(index) => format!(
+            "{}{}",
+            api_base.trim_end_matches('/'),
+            &url[index + API_PATH.len()..]
+        ),
+        None => url.to_string(),
+    }
+}
+
+// 设置 URL 中的查询参数，已存在时替换
+pub fn set_query_param(url: &str, key: &str, value: &str) -> String {
+    let (base, query) = match url.split_once('?') {
+        Some((base, query)) => (base, query),
+        None => (url, ""),
+    };
+
+    let mut params: Vec<String> = query
+        .split('&')
+        .filter(|p| !p.is_empty() && p.split('=').next() != Some(key))
+        .map(str::to_string)
+        .collect();
+    params.push(format!("{}={}", key, value));
+
+    format!("{}?{}", base, params.join("&"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const API: &str = "http://proxy.local/gitlab/api/v4";
+    const CURRENT: &str = "http://proxy.local/gitlab/api/v4/projects/1/repository/commits?per_page=100&page=1";
+
+    #[test]
+    fn follows_link_header_through_proxy() {
+        let response = HttpResponse::json(200, "[]")
+            .with_header(
+                "Link",
+                r#"<https://gitlab.example.com/api/v4/projects/1/repository/commits?per_page=100&page=2>; rel="next", <https://gitlab.example.com/api/v4/projects/1/repository/commits?per_page=100&page=1>; rel="first""#,
+            )
+            .with_header("X-Total", "250");
+        let info = page_info(&response, CURRENT, API);
+        assert_eq!(
+            info.next_url.as_deref(),
+            Some("http://proxy.local/gitlab/api/v4/projects/1/repository/commits?per_page=100&page=2")
+        );
+        assert_eq!(info.total, Some(250));
+        assert!(info.has_headers);
+    }
+
+    #[test]
+    fn falls_back_to_next_page_header() {
+        let response = HttpResponse::json(200, "[]").with_header("X-Next-Page", "3");
+        let info = page_info(&response, CURRENT, API);
+        assert_eq!(
+            info.next_url.as_deref(),
+            Some("http://proxy.local/gitlab/api/v4/projects/1/repository/commits?per_page=100&page=3")
+        );
+        assert_eq!(info.total, None);
+    }
+
+    #[test]
+    fn last_page_has_no_next_url() {
+        let response = HttpResponse::json(200, "[]")
+            .with_header("Link", r#"<https://gitlab.example.com/api/v4/x?page=1>; rel="first""#)
+            .with_header("X-Next-Page", "");
+        let info = page_info(&response, CURRENT, API);
+        assert!(info.next_url.is_none());
+        assert!(info.has_headers);
+
+        let info = page_info(&HttpResponse::json(200, "[]"), CURRENT, API);
+        assert!(info.next_url.is_none());
+        assert!(!info.has_headers);
+    }
+
+    #[test]
+    fn stops_when_next_page_repeats_current_url() {
+        let response = HttpResponse::json(200, "[]").with_header("X-Next-Page", "1");
+        assert!(page_info(&response, CURRENT, API).next_url.is_none());
+    }
+
+    #[test]
+    fn replaces_existing_query_param() {
+        assert_eq!(set_query_param("http://a/b?page=1&per_page=20", "page", "2"), "http://a/b?per_page=20&page=2");
+        assert_eq!(set_query_param("http://a/b", "page", "2"), "http://a/b?page=2");
+    }
+}
@@ -0,0 +1,224 @@
+// 文件路径过滤
+// ignored_paths 和 valid_extensions 支持 gitignore 风格的规则，分析开始时编译一次：
+//   不含 / 的规则匹配任意层级的文件名或目录名，例如 build/、Dockerfile、*.min.js
+//   含 / 的规则从仓库根目录开始匹配，例如 src/**/*.ts、/docs
+//   以 / 结尾的规则只匹配目录，* 和 ? 不匹配 /，** 匹配任意层级目录，支持 [abc] 字符集
+//   以 ! 开头的规则取反，多条规则匹配同一路径时以最后一条为准
+//   以 re: 开头的规则为正则表达式，在完整路径中查找
+// valid_extensions 中形如 .ts 的扩展名按 *.ts 处理，只匹配文件名
+
+use regex::Regex;
+
+#[derive(Debug)]
+struct Rule {
+    regex: Regex,
+    negated: bool,
+}
+
+#[derive(Debug)]
+pub struct PathFilter {
+    // valid_extensions，为空时统计所有文件
+    includes: Vec<Rule>,
+    ignores: Vec<Rule>,
+}
+
+impl PathFilter {
+    pub fn new(valid_extensions: &[String], ignored_paths: &[String]) -> Result<Self, String> {
+        let includes = valid_extensions
+            .iter()
+            .map(|pattern| compile(pattern, true))
+            .collect::<Result<_, _>>()?;
+        let ignores = ignored_paths
+            .iter()
+            .map(|pattern| compile(pattern, false))
+            .collect::<Result<_, _>>()?;
+        Ok(PathFilter { includes, ignores })
+    }
+
+    // 文件是否需要统计
+    pub fn is_included(&self, path: &str) -> bool {
+        if matches(&self.ignores, path) == Some(true) {
+            return false;
+        }
+        if self.includes.is_empty() {
+            return true;
+        }
+        match matches(&self.includes, path) {
+            Some(included) => included,
+            // 只有取反规则时，没有命中的文件都统计
+            None => self.includes.iter().all(|rule| rule.negated),
+        }
+    }
+}
+
+// 按最后一条匹配的规则返回是否命中，没有规则匹配时返回 None
+fn matches(rules: &[Rule], path: &str) -> Option<bool> {
+    rules
+        .iter()
+        .rev()
+        .find(|rule| rule.regex.is_match(path))
+        .map(|rule| !rule.negated)
+}
+
+fn compile(pattern: &str, extension_shorthand: bool) -> Result<Rule, String> {
+    let invalid = |e: regex::Error| format!("路径规则 {} 无效: {}", pattern, e);
+    let (negated, body) = match pattern.trim().strip_prefix('!') {
+        Some(body) => (true, body),
+        None => (false, pattern.trim()),
+    };
+
+    if let Some(regex) = body.strip_prefix("re:") {
+        let regex = Regex::new(regex).map_err(invalid)?;
+        return Ok(Rule { regex, negated });
+    }
+
+    // 扩展名只匹配文件本身，不匹配 three.js/ 这类同名目录下的文件
+    if extension_shorthand && body.starts_with('.') && !body.contains(['/', '*', '?', '[']) {
+        let regex = glob_regex(&format!("*{}", body), false).map_err(invalid)?;
+        return Ok(Rule { regex, negated });
+    }
+
+    let regex = glob_regex(body, true).map_err(invalid)?;
+    Ok(Rule { regex, negated })
+}
+
+// 把 gitignore 风格的规则转为正则
+// match_dirs 为 true 时规则也匹配目录，目录下的所有文件都命中；为 false 时只匹配文件本身（.gitattributes 的规则）
+pub fn glob_regex(pattern: &str, match_dirs: bool) -> Result<Regex, regex::Error> {
+    let dir_only = pattern.ends_with('/');
+    let body = pattern.trim_end_matches('/');
+    // 中间或开头带 / 的规则相对于根目录
+    let anchored = body.contains('/');
+    let body = body.trim_start_matches('/');
+
+    let mut regex = String::from(if anchored { "^" } else { "(?:^|/)" });
+    regex.push_str(&glob_to_regex(body));
+    regex.push_str(match (match_dirs, dir_only) {
+        (true, true) => "/",
+        (true, false) => "(?:/|$)",
+        (false, _) => "$",
+    });
+    Regex::new(&regex)
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut regex = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                // **/ 匹配零或多层目录，其余位置的 ** 匹配任意字符
+                if chars.get(i + 2) == Some(&'/') {
+                    regex.push_str("(?:[^/]*/)*");
+                    i += 3;
+                } else {
+                    regex.push_str(".*");
+                    i += 2;
+                }
+                continue;
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => match chars[i + 1..].iter().position(|&c| c == ']') {
+                Some(end) => {
+                    let class: String = chars[i + 1..i + 1 + end].iter().collect();
+                    let class = class.strip_prefix('!').map(|rest| format!("^{}", rest)).unwrap_or(class);
+                    regex.push('[');
+                    regex.push_str(&class.replace('\\', "\\\\"));
+                    regex.push(']');
+                    i += end + 2;
+                    continue;
+                }
+                None => regex.push_str(r"\["),
+            },
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(valid_extensions: &[&str], ignored_paths: &[&str]) -> PathFilter {
+        let strings = |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        PathFilter::new(&strings(valid_extensions), &strings(ignored_paths)).unwrap()
+    }
+
+    #[test]
+    fn extension_shorthand_matches_files_only() {
+        let filter = filter(&[".ts", ".js"], &[]);
+        assert!(filter.is_included("src/app.ts"));
+        assert!(filter.is_included("app.js"));
+        assert!(!filter.is_included("dir.ts/readme.md"));
+        assert!(!filter.is_included("vendor/three.js/LICENSE"));
+        assert!(!filter.is_included("src/app.tsx"));
+    }
+
+    #[test]
+    fn unanchored_rules_match_any_level() {
+        let filter = filter(&[], &["dist", "build/", "*lintrc*"]);
+        assert!(!filter.is_included("dist/app.js"));
+        assert!(!filter.is_included("packages/web/dist/app.js"));
+        assert!(!filter.is_included("packages/web/build/app.js"));
+        assert!(!filter.is_included(".eslintrc.json"));
+        assert!(filter.is_included("src/rebuild/app.js"));
+        assert!(filter.is_included("distribution/app.js"));
+        // 以 / 结尾的规则不匹配同名文件
+        assert!(filter.is_included("scripts/build"));
+    }
+
+    #[test]
+    fn anchored_rules_and_double_star() {
+        let filter = filter(&["src/**/*.ts"], &["/docs"]);
+        assert!(filter.is_included("src/app.ts"));
+        assert!(filter.is_included("src/a/b/app.ts"));
+        assert!(!filter.is_included("lib/src/app.ts"));
+        let filter = self::filter(&[], &["/docs"]);
+        assert!(!filter.is_included("docs/index.md"));
+        assert!(filter.is_included("web/docs/index.md"));
+    }
+
+    #[test]
+    fn last_matching_rule_wins() {
+        let filter = filter(&[".ts", "!**/*.spec.ts"], &["generated/", "!generated/keep.ts"]);
+        assert!(filter.is_included("src/app.ts"));
+        assert!(!filter.is_included("src/app.spec.ts"));
+        assert!(!filter.is_included("generated/api.ts"));
+        assert!(filter.is_included("generated/keep.ts"));
+    }
+
+    #[test]
+    fn negations_only_include_everything_else() {
+        let filter = filter(&["!*.md"], &[]);
+        assert!(filter.is_included("src/app.rs"));
+        assert!(!filter.is_included("README.md"));
+    }
+
+    #[test]
+    fn regex_rules_and_character_classes() {
+        let filter = filter(&[], &[r"re:\.snap$", "tmp[0-9]/", "cache[!a-z]"]);
+        assert!(!filter.is_included("src/__snapshots__/app.test.js.snap"));
+        assert!(!filter.is_included("tmp1/a.txt"));
+        assert!(filter.is_included("tmpx/a.txt"));
+        assert!(!filter.is_included("cache1"));
+        assert!(filter.is_included("cachex"));
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        assert!(PathFilter::new(&[], &["re:(".to_string()]).is_err());
+    }
+
+    #[test]
+    fn gitattributes_globs_do_not_match_directories() {
+        let regex = glob_regex("*.js", false).unwrap();
+        assert!(regex.is_match("a/b.js"));
+        assert!(!regex.is_match("three.js/readme.md"));
+        let regex = glob_regex("*.js", true).unwrap();
+        assert!(regex.is_match("three.js/readme.md"));
+    }
+}
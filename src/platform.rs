@@ -0,0 +1,59 @@
+// 运行环境相关的辅助函数
+// wasm 环境下使用 js 的能力，其他环境（如原生测试）下使用标准库实现，保证核心流程不依赖 JsValue
+
+#[cfg(target_arch = "wasm32")]
+use web_sys::console;
+
+// 当前时间戳（毫秒）
+#[cfg(target_arch = "wasm32")]
+pub fn now_ms() -> f64 {
+    js_sys::Date::now()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now_ms() -> f64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64() * 1000.0)
+        .unwrap_or_default()
+}
+
+// 输出普通日志
+pub fn log(msg: &str) {
+    #[cfg(target_arch = "wasm32")]
+    console::log_1(&msg.into());
+    #[cfg(not(target_arch = "wasm32"))]
+    println!("{}", msg);
+}
+
+// 输出错误日志
+pub fn log_error(msg: &str) {
+    #[cfg(target_arch = "wasm32")]
+    console::error_1(&msg.into());
+    #[cfg(not(target_arch = "wasm32"))]
+    eprintln!("{}", msg);
+}
+
+// [0, 1) 之间的随机数，用于重试抖动
+#[cfg(target_arch = "wasm32")]
+pub fn random() -> f64 {
+    js_sys::Math::random()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn random() -> f64 {
+    use std::cell::Cell;
+    thread_local! {
+        static SEED: Cell<u64> = Cell::new(now_ms() as u64 | 1);
+    }
+    // xorshift，仅用于抖动，不要求随机质量
+    SEED.with(|seed| {
+        let mut x = seed.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        seed.set(x);
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    })
+}
@@ -0,0 +1,115 @@
+// 分析进度事件
+// 通过 Config 中的 on_progress 回调把结构化的进度事件传给调用方，用于渲染进度条，而不是解析控制台日志
+
+use crate::platform::now_ms;
+use serde::Serialize;
+use std::cell::Cell;
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum ProgressEvent {
+    // 获取项目列表完成
+    ProjectsDiscovered {
+        // 群组下的项目总数，未知时为空
+        total: Option<u32>,
+        // 本次需要分析的项目数
+        analyzed: u32,
+    },
+    // 开始分析某个项目
+    ProjectStarted {
+        project: String,
+        index: u32,
+        total: u32,
+    },
+    // 获取到一页提交记录
+    CommitsFetched {
+        project: String,
+        fetched: u32,
+        total: Option<u32>,
+    },
+    // 处理完项目中的一个提交
+    DiffsProcessed {
+        project: String,
+        processed: u32,
+        total: u32,
+        // 该项目预计剩余时间（毫秒）
+        eta_ms: Option<f64>,
+    },
+    // 项目分析完成
+    ProjectFinished {
+        project: String,
+        finished: u32,
+        total: u32,
+        // 整体预计剩余时间（毫秒）
+        eta_ms: Option<f64>,
+    },
+    // 请求失败，即将重试
+    Retry {
+        operation: String,
+        url: String,
+        attempt: u32,
+        max_attempts: u32,
+        delay_ms: f64,
+    },
+    // 请求最终失败，已记录到 failureStats
+    Failure {
+        operation: String,
+        url: String,
+        project: Option<String>,
+        error: String,
+    },
+    // 分析被取消，即将返回已完成部分的报告
+    Cancelled,
+}
+
+// 进度回调
+pub type ProgressCallback = Box<dyn Fn(&ProgressEvent)>;
+
+// 记录整体进度并派发事件
+pub struct ProgressTracker {
+    callback: Option<ProgressCallback>,
+    started_at: f64,
+    total_projects: Cell<u32>,
+    finished_projects: Cell<u32>,
+}
+
+impl ProgressTracker {
+    pub fn new(callback: Option<ProgressCallback>) -> Self {
+        ProgressTracker {
+            callback,
+            started_at: now_ms(),
+            total_projects: Cell::new(0),
+            finished_projects: Cell::new(0),
+        }
+    }
+
+    pub fn emit(&self, event: ProgressEvent) {
+        if let Some(callback) = &self.callback {
+            callback(&event);
+        }
+    }
+
+    pub fn set_total_projects(&self, total: u32) {
+        self.total_projects.set(total);
+    }
+
+    pub fn total_projects(&self) -> u32 {
+        self.total_projects.get()
+    }
+
+    // 标记一个项目完成，返回已完成数和整体预计剩余时间
+    pub fn finish_project(&self) -> (u32, Option<f64>) {
+        let finished = self.finished_projects.get() + 1;
+        self.finished_projects.set(finished);
+        (finished, eta(self.started_at, finished, self.total_projects.get()))
+    }
+}
+
+// 按已完成部分的平均耗时估算剩余时间
+pub fn eta(started_at: f64, done: u32, total: u32) -> Option<f64> {
+    if done == 0 || done > total {
+        return None;
+    }
+    let elapsed = now_ms() - started_at;
+    Some((elapsed / done as f64 * (total - done) as f64).round())
+}
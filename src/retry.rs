@@ -0,0 +1,147 @@
+// 重试与限流相关的辅助逻辑
+// GitLab 在响应头中返回 RateLimit-Remaining / RateLimit-Reset，触发限流时返回 429 和 Retry-After
+// 触发限流后暂停所有请求，而不是让每个请求各自重试，避免 token 被进一步限流
+
+use crate::platform::random;
+use crate::transport::HttpResponse;
+use std::sync::Mutex;
+
+// 默认的重试退避参数（毫秒），可通过 Config.retry 覆盖
+pub const DEFAULT_BASE_BACKOFF: u32 = 300;
+pub const DEFAULT_MAX_BACKOFF: u32 = 30_000;
+
+// 全局限流状态，所有请求共享
+#[derive(Default)]
+pub struct RateLimitState {
+    // 在该时间戳（毫秒）之前暂停发起请求
+    paused_until: Mutex<f64>,
+}
+
+impl RateLimitState {
+    // 距离恢复请求还需等待的毫秒数
+    pub fn wait_time(&self, now: f64) -> f64 {
+        let paused_until = *self.paused_until.lock().unwrap();
+        (paused_until - now).max(0.0)
+    }
+
+    // 根据响应头更新限流状态，返回本次需要暂停到的时间戳
+    pub fn update(&self, response: &HttpResponse, now: f64) -> Option<f64> {
+        let until = rate_limit_pause(response, now)?;
+        let mut paused_until = self.paused_until.lock().unwrap();
+        if until > *paused_until {
+            *paused_until = until;
+        }
+        Some(*paused_until)
+    }
+}
+
+// 计算触发限流时需要暂停到的时间戳
+// 429 优先使用 Retry-After，其次使用 RateLimit-Reset
+// 请求成功但 RateLimit-Remaining 已经为 0 时，提前暂停到 RateLimit-Reset
+fn rate_limit_pause(response: &HttpResponse, now: f64) -> Option<f64> {
+    let retry_after = response
+        .header("Retry-After")
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .map(|secs| now + secs * 1000.0);
+    let reset = response
+        .header("RateLimit-Reset")
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .map(|secs| secs * 1000.0)
+        .filter(|reset| *reset > now);
+    let exhausted = response
+        .header("RateLimit-Remaining")
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .is_some_and(|remaining| remaining <= 0);
+
+    if response.status == 429 {
+        retry_after.or(reset)
+    } else if exhausted {
+        reset.or(retry_after)
+    } else {
+        None
+    }
+}
+
+// 请求超时、限流和服务端错误可以重试，鉴权失败、资源不存在等重试也不会成功
+pub fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 408 | 425 | 429) || status >= 500
+}
+
+// 指数退避并加上随机抖动，第 attempt 次失败后的等待时间
+pub fn backoff_delay(attempt: u32, base: u32, max: u32) -> i32 {
+    let exp = (base as f64) * 2f64.powi(attempt.saturating_sub(1).min(16) as i32);
+    let capped = exp.min(max as f64);
+    // 在 [capped / 2, capped) 之间随机，避免并发请求同时重试
+    (capped / 2.0 + random() * capped / 2.0) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: f64 = 1_700_000_000_000.0;
+
+    fn response(status: u16) -> HttpResponse {
+        HttpResponse::json(status, "")
+    }
+
+    #[test]
+    fn too_many_requests_prefers_retry_after() {
+        let response = response(429)
+            .with_header("Retry-After", "5")
+            .with_header("RateLimit-Reset", "1700000060");
+        assert_eq!(rate_limit_pause(&response, NOW), Some(NOW + 5000.0));
+    }
+
+    #[test]
+    fn too_many_requests_falls_back_to_reset() {
+        let response = response(429).with_header("RateLimit-Reset", "1700000060");
+        assert_eq!(rate_limit_pause(&response, NOW), Some(1_700_000_060_000.0));
+    }
+
+    #[test]
+    fn exhausted_quota_waits_until_reset() {
+        let response = response(200)
+            .with_header("RateLimit-Remaining", "0")
+            .with_header("RateLimit-Reset", "1700000030")
+            .with_header("Retry-After", "5");
+        assert_eq!(rate_limit_pause(&response, NOW), Some(1_700_000_030_000.0));
+    }
+
+    #[test]
+    fn remaining_quota_does_not_pause() {
+        let response = response(200)
+            .with_header("RateLimit-Remaining", "10")
+            .with_header("RateLimit-Reset", "1700000030");
+        assert_eq!(rate_limit_pause(&response, NOW), None);
+        assert_eq!(rate_limit_pause(&self::response(200), NOW), None);
+    }
+
+    #[test]
+    fn ignores_reset_in_the_past() {
+        let response = response(200)
+            .with_header("RateLimit-Remaining", "0")
+            .with_header("RateLimit-Reset", "1699999990");
+        assert_eq!(rate_limit_pause(&response, NOW), None);
+    }
+
+    #[test]
+    fn state_keeps_the_latest_pause() {
+        let state = RateLimitState::default();
+        state.update(&response(429).with_header("Retry-After", "10"), NOW);
+        state.update(&response(429).with_header("Retry-After", "2"), NOW);
+        assert_eq!(state.wait_time(NOW), 10_000.0);
+        assert_eq!(state.wait_time(NOW + 20_000.0), 0.0);
+    }
+
+    #[test]
+    fn backoff_is_capped_with_jitter() {
+        for attempt in 1..20 {
+            let delay = backoff_delay(attempt, 300, 30_000);
+            let capped = (300.0 * 2f64.powi(attempt as i32 - 1)).min(30_000.0);
+            assert!(delay as f64 >= capped / 2.0 && (delay as f64) < capped);
+        }
+        assert!(is_retryable_status(503) && is_retryable_status(429));
+        assert!(!is_retryable_status(404) && !is_retryable_status(401));
+    }
+}
@@ -0,0 +1,246 @@
+// 回滚提交识别
+// 提交信息中包含 "This reverts commit <sha>" 时指向被回滚的提交，
+// 包含 "This reverts merge request !<iid>" 时（GitLab 中回滚合并请求）指向该合并请求的所有提交
+// 回滚提交和被回滚的提交按 RevertPolicy 统计，被回滚提交的代码行记录在原作者的 revertedLines 中
+
+use crate::{fetch_json, fetch_paginated, pagination, AnalysisContext, AnalysisError, Commit, Project};
+use crate::{RequestContext, RequestKind};
+use futures::future::join_all;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+static REVERT_COMMIT_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)This reverts commit ([0-9a-f]{7,40})").unwrap());
+static REVERT_MR_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)This reverts merge request !(\d+)").unwrap());
+
+// 回滚提交和被回滚提交的统计方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RevertPolicy {
+    // 两边的代码行相互抵消，都不计入代码量，但仍计入提交数
+    #[default]
+    Net,
+    // 两边都不统计
+    Exclude,
+    // 与普通提交一样统计
+    Count,
+}
+
+// 提交在回滚关系中的角色
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RevertRole {
+    // 回滚了其他提交
+    pub is_revert: bool,
+    // 被其他提交回滚
+    pub is_reverted: bool,
+}
+
+// 一个项目中的回滚关系
+#[derive(Debug, Default)]
+pub struct Reverts {
+    reverts: HashSet<String>,
+    reverted: HashSet<String>,
+}
+
+impl Reverts {
+    pub fn role(&self, sha: &str) -> RevertRole {
+        RevertRole {
+            is_revert: self.reverts.contains(sha),
+            is_reverted: self.reverted.contains(sha),
+        }
+    }
+
+    // 回滚提交数
+    pub fn revert_count(&self) -> usize {
+        self.reverts.len()
+    }
+}
+
+#[derive(Deserialize)]
+struct MergeRequest {
+    merge_commit_sha: Option<String>,
+    squash_commit_sha: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MergeRequestCommit {
+    id: String,
+}
+
+// 找出项目提交中的回滚关系，被回滚的提交需要在同一个列表中才会关联
+pub(crate) async fn find_reverts(ctx: &AnalysisContext, project: &Project, commits: &[Commit]) -> Reverts {
+    let (mut reverts, merge_requests) = parse_reverts(commits);
+
+    // 回滚合并请求时需要查询合并请求包含的提交
+    let futures: Vec<_> = merge_requests
+        .iter()
+        .map(|(_, iid)| merge_request_commits(ctx, project, iid))
+        .collect();
+    for ((revert_sha, iid), result) in merge_requests.iter().zip(join_all(futures).await) {
+        match result {
+            Ok(shas) => reverts.link(commits, revert_sha, &shas),
+            Err(e) => crate::log_error(&format!("[获取合并请求 !{} 失败] {} {}", iid, project.name, e)),
+        }
+    }
+
+    reverts
+}
+
+// 按提交信息中的 sha 找出回滚关系，同时返回回滚了合并请求的提交及合并请求的 iid
+fn parse_reverts(commits: &[Commit]) -> (Reverts, Vec<(String, String)>) {
+    let mut reverts = Reverts::default();
+    let mut merge_requests = Vec::new();
+    for commit in commits {
+        let targets: Vec<&str> = REVERT_COMMIT_RE
+            .captures_iter(&commit.message)
+            .map(|c| c.get(1).unwrap().as_str())
+            .collect();
+        reverts.link(commits, &commit.id, &targets);
+        if let Some(captures) = REVERT_MR_RE.captures(&commit.message) {
+            merge_requests.push((commit.id.clone(), captures[1].to_string()));
+        }
+    }
+    (reverts, merge_requests)
+}
+
+impl Reverts {
+    // 记录 revert_sha 回滚了 targets，不在提交列表中的提交和提交自身忽略
+    fn link<S: AsRef<str>>(&mut self, commits: &[Commit], revert_sha: &str, targets: &[S]) {
+        // 提交信息中可能是缩写的 sha，按前缀匹配
+        let find_commit = |sha: &str| {
+            let sha = sha.to_lowercase();
+            commits.iter().find(|c| c.id.starts_with(&sha)).map(|c| c.id.clone())
+        };
+        let targets: Vec<_> = targets
+            .iter()
+            .filter_map(|sha| find_commit(sha.as_ref()))
+            .filter(|sha| sha != revert_sha)
+            .collect();
+        if !targets.is_empty() {
+            self.reverts.insert(revert_sha.to_string());
+            self.reverted.extend(targets);
+        }
+    }
+}
+
+// 合并请求的所有提交，以及合并后产生的合并提交和压缩提交
+async fn merge_request_commits(
+    ctx: &AnalysisContext,
+    project: &Project,
+    iid: &str,
+) -> Result<Vec<String>, AnalysisError> {
+    let base = format!(
+        "{}/projects/{}/merge_requests/{}",
+        ctx.config.gitlab_api, project.id, iid
+    );
+    let context = RequestContext {
+        kind: RequestKind::Commits,
+        operation: "获取被回滚的合并请求".to_string(),
+        project_name: Some(project.name.clone()),
+        author: None,
+    };
+
+    let merge_request: MergeRequest = fetch_json(ctx, &base, &context).await?;
+    let commits = fetch_paginated::<MergeRequestCommit>(
+        ctx,
+        format!("{}/commits?per_page={}", base, pagination::MAX_PER_PAGE),
+        pagination::MAX_PER_PAGE,
+        &context,
+        None,
+        |_, _| {},
+    )
+    .await?;
+
+    let mut shas: Vec<_> = commits.items.into_iter().map(|c| c.id).collect();
+    shas.extend(merge_request.merge_commit_sha);
+    shas.extend(merge_request.squash_commit_sha);
+    Ok(shas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(sha: &str, message: &str) -> Commit {
+        serde_json::from_value(serde_json::json!({
+            "id": sha,
+            "author_name": "Alice",
+            "author_email": "alice@example.com",
+            "message": message,
+            "committed_date": "2024-01-10T10:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    fn role(reverts: &Reverts, sha: &str) -> (bool, bool) {
+        let role = reverts.role(sha);
+        (role.is_revert, role.is_reverted)
+    }
+
+    #[test]
+    fn links_full_and_short_shas() {
+        let commits = [
+            commit("aaaaaaa1111111111111111111111111111111111", "feat: a"),
+            commit("bbbbbbb2222222222222222222222222222222222", "feat: b"),
+            commit(
+                "ccccccc3333333333333333333333333333333333",
+                "Revert \"feat: a\"\n\nThis reverts commit AAAAAAA1111111111111111111111111111111111.",
+            ),
+            commit("ddddddd4444444444444444444444444444444444", "Revert b\n\nthis reverts commit bbbbbbb."),
+        ];
+        let (reverts, merge_requests) = parse_reverts(&commits);
+        assert!(merge_requests.is_empty());
+        assert_eq!(reverts.revert_count(), 2);
+        assert_eq!(role(&reverts, &commits[0].id), (false, true));
+        assert_eq!(role(&reverts, &commits[1].id), (false, true));
+        assert_eq!(role(&reverts, &commits[2].id), (true, false));
+        assert_eq!(role(&reverts, &commits[3].id), (true, false));
+    }
+
+    #[test]
+    fn revert_of_a_revert_has_both_roles() {
+        let commits = [
+            commit("a000000", "feat: a"),
+            commit("b000000", "This reverts commit a000000."),
+            commit("c000000", "This reverts commit b000000."),
+        ];
+        let (reverts, _) = parse_reverts(&commits);
+        assert_eq!(role(&reverts, "a000000"), (false, true));
+        assert_eq!(role(&reverts, "b000000"), (true, true));
+        assert_eq!(role(&reverts, "c000000"), (true, false));
+    }
+
+    #[test]
+    fn ignores_unknown_short_and_self_references() {
+        let commits = [
+            commit("a000000", "This reverts commit a000000."),
+            commit("b000000", "This reverts commit 1234567."),
+            // 少于 7 位的 sha 不识别
+            commit("c000000", "This reverts commit a0000."),
+        ];
+        let (reverts, _) = parse_reverts(&commits);
+        assert_eq!(reverts.revert_count(), 0);
+        assert_eq!(role(&reverts, "a000000"), (false, false));
+    }
+
+    #[test]
+    fn collects_merge_request_reverts() {
+        let commits = [
+            commit("a000000", "feat: a"),
+            commit("m000000", "Merge branch 'a' into 'main'"),
+            commit("r000000", "Revert \"Merge branch 'a'\"\n\nThis reverts merge request !42"),
+        ];
+        let (mut reverts, merge_requests) = parse_reverts(&commits);
+        assert_eq!(merge_requests, vec![("r000000".to_string(), "42".to_string())]);
+        assert_eq!(reverts.revert_count(), 0);
+
+        // 合并请求中不在提交列表里的提交忽略
+        reverts.link(&commits, "r000000", &["a000000", "m000000", "f000000", "r000000"]);
+        assert_eq!(role(&reverts, "r000000"), (true, false));
+        assert_eq!(role(&reverts, "a000000"), (false, true));
+        assert_eq!(role(&reverts, "m000000"), (false, true));
+    }
+}
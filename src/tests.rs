@@ -0,0 +1,746 @@
+// 通过 MemoryTransport 回放 GitLab 接口，验证完整的分析流程
+
+use super::*;
+use futures::executor::block_on;
+use cache::MemoryCache;
+use cancel::CancelToken;
+use progress::ProgressEvent;
+use transport::MemoryTransport;
+
+const API: &str = "http://gitlab.test/api/v4";
+
+fn config(extra: serde_json::Value) -> Config {
+    let mut value = serde_json::json!({
+        "gitlab_api": API,
+        "gitlab_token": "token",
+        "group_id": "1",
+        "start_date": "2024-01-01",
+        "end_date": "2024-02-01",
+        "projects_num": 10,
+        "excluded_projects": [],
+        "valid_extensions": [],
+        "max_concurrent_requests": 3,
+        "ignored_paths": [],
+    });
+    if let (Some(value), serde_json::Value::Object(extra)) = (value.as_object_mut(), extra) {
+        value.extend(extra);
+    }
+    serde_json::from_value(value).unwrap()
+}
+
+fn projects_url() -> String {
+    format!(
+        "{}/groups/1/projects?per_page=10&include_subgroups=true&order_by=last_activity_at&sort=desc",
+        API
+    )
+}
+
+fn commits_url(project_id: i64) -> String {
+    format!(
+        "{}/projects/{}/repository/commits?since=2024-01-01&until=2024-02-01&per_page=100&all=true",
+        API, project_id
+    )
+}
+
+fn commit_json(sha: &str, name: &str, email: &str, message: &str) -> serde_json::Value {
+    serde_json::json!({
+        "id": sha,
+        "author_name": name,
+        "author_email": email,
+        "message": message,
+        "committed_date": "2024-01-10T10:00:00Z",
+        "parent_ids": ["parent"],
+    })
+}
+
+// 预设提交的差异和分支信息
+fn respond_commit(transport: &MemoryTransport, project_id: i64, sha: &str, diffs: serde_json::Value) {
+    let base = format!("{}/projects/{}/repository/commits/{}", API, project_id, sha);
+    transport.respond(format!("{}/diff", base), HttpResponse::json(200, diffs.to_string()));
+    transport.respond(
+        format!("{}/refs", base),
+        HttpResponse::json(200, r#"[{"type":"branch","name":"main"},{"type":"tag","name":"v1.0"}]"#),
+    );
+}
+
+fn added_lines(path: &str, lines: usize) -> serde_json::Value {
+    let body: String = (0..lines).map(|i| format!("+line {}\n", i)).collect();
+    serde_json::json!({ "new_path": path, "diff": format!("@@ -0,0 +1,{} @@\n{}", lines, body) })
+}
+
+fn analyze(config: Config, transport: &Rc<MemoryTransport>, options: AnalysisOptions) -> Report {
+    block_on(analyze_with_transport(config, transport.clone(), options)).unwrap()
+}
+
+fn total_row<'a>(report: &'a Report, email: &str) -> &'a CodeStat {
+    report
+        .code_stats
+        .iter()
+        .find(|stat| stat.email == email)
+        .unwrap_or_else(|| panic!("没有 {} 的统计", email))
+}
+
+#[test]
+fn analyzes_projects_through_memory_transport() {
+    let transport = Rc::new(MemoryTransport::new());
+    transport.respond(
+        projects_url(),
+        HttpResponse::json(200, r#"[{"id":1,"name":"web"},{"id":2,"name":"legacy"}]"#),
+    );
+    transport.respond(
+        commits_url(1),
+        HttpResponse::json(
+            200,
+            serde_json::json!([
+                commit_json("a1", "Alice", "alice@example.com", "feat: login"),
+                commit_json("a2", "Alice", "alice@example.com", "fix: typo"),
+                commit_json("b1", "Bob", "bob@example.com", "docs: readme"),
+            ])
+            .to_string(),
+        ),
+    );
+    respond_commit(&transport, 1, "a1", serde_json::json!([added_lines("src/login.ts", 3)]));
+    respond_commit(
+        &transport,
+        1,
+        "a2",
+        serde_json::json!([{ "new_path": "src/login.ts", "diff": "@@ -1 +1 @@\n-tpyo\n+typo\n" }]),
+    );
+    respond_commit(&transport, 1, "b1", serde_json::json!([added_lines("README.md", 2)]));
+    transport.respond(commits_url(2), HttpResponse::json(200, "[]"));
+
+    let report = analyze(
+        config(serde_json::json!({ "excluded_projects": ["legacy"] })),
+        &transport,
+        AnalysisOptions::default(),
+    );
+
+    assert_eq!(report.project_summary.fetched, 2);
+    assert_eq!(report.project_summary.excluded, 1);
+    assert_eq!(report.project_summary.analyzed, 1);
+    assert!(report.failure_stats.is_none());
+    assert!(!report.cancelled);
+
+    let alice = total_row(&report, "alice@example.com");
+    assert_eq!(alice.commits, 2);
+    assert_eq!((alice.additions, alice.deletions, alice.lines), (4, 1, 5));
+    assert_eq!(alice.hunks, 2);
+    let bob = total_row(&report, "bob@example.com");
+    assert_eq!((bob.commits, bob.lines), (1, 2));
+
+    assert_eq!(report.commit_stats.len(), 3);
+    assert!(report
+        .commit_stats
+        .iter()
+        .all(|commit| commit.branch == "main" && commit.tag == "v1.0" && commit.project == "web"));
+
+    // 被排除的项目不会请求提交记录
+    assert!(!transport.requests().contains(&commits_url(2)));
+}
+
+#[test]
+fn records_failed_diff_requests() {
+    let transport = Rc::new(MemoryTransport::new());
+    transport.respond(projects_url(), HttpResponse::json(200, r#"[{"id":1,"name":"web"}]"#));
+    transport.respond(
+        commits_url(1),
+        HttpResponse::json(
+            200,
+            serde_json::json!([
+                commit_json("a1", "Alice", "alice@example.com", "feat: ok"),
+                commit_json("a2", "Alice", "alice@example.com", "feat: missing diff"),
+            ])
+            .to_string(),
+        ),
+    );
+    respond_commit(&transport, 1, "a1", serde_json::json!([added_lines("a.ts", 1)]));
+    // a2 的差异未预设，MemoryTransport 返回 404，不会重试
+    transport.respond(
+        format!("{}/projects/1/repository/commits/a2/refs", API),
+        HttpResponse::json(200, "[]"),
+    );
+
+    let report = analyze(config(serde_json::json!({})), &transport, AnalysisOptions::default());
+
+    let failures = report.failure_stats.as_ref().expect("应当记录失败的请求");
+    assert_eq!(failures.len(), 1);
+    assert!(failures[0].url.contains("/commits/a2/diff"));
+    assert_eq!(failures[0].project_name.as_deref(), Some("web"));
+    assert_eq!(total_row(&report, "alice@example.com").commits, 1);
+}
+
+#[test]
+fn cache_serves_diffs_but_not_refs() {
+    let transport = Rc::new(MemoryTransport::new());
+    transport.respond(projects_url(), HttpResponse::json(200, r#"[{"id":1,"name":"web"}]"#));
+    transport.respond(
+        commits_url(1),
+        HttpResponse::json(
+            200,
+            serde_json::json!([commit_json("a1", "Alice", "alice@example.com", "feat: cached")]).to_string(),
+        ),
+    );
+    respond_commit(&transport, 1, "a1", serde_json::json!([added_lines("a.ts", 2)]));
+    let cache: Rc<MemoryCache> = Rc::new(MemoryCache::new());
+    let options = || AnalysisOptions {
+        cache: Some(cache.clone()),
+        ..AnalysisOptions::default()
+    };
+
+    analyze(config(serde_json::json!({})), &transport, options());
+    assert_eq!(cache.len(), 1);
+
+    // 第二次分析期间打了新标签，差异从缓存读取，refs 重新请求
+    let diff_url = format!("{}/projects/1/repository/commits/a1/diff", API);
+    let refs_url = format!("{}/projects/1/repository/commits/a1/refs", API);
+    transport.respond(
+        refs_url.clone(),
+        HttpResponse::json(200, r#"[{"type":"branch","name":"main"},{"type":"tag","name":"v2.0"}]"#),
+    );
+    let before = transport.requests().len();
+    let report = analyze(config(serde_json::json!({})), &transport, options());
+    let requests = &transport.requests()[before..];
+
+    assert!(!requests.contains(&diff_url));
+    assert!(requests.contains(&refs_url));
+    assert_eq!(report.commit_stats[0].tag, "v2.0");
+    assert_eq!(total_row(&report, "alice@example.com").lines, 2);
+}
+
+#[test]
+fn duplicate_commits_are_counted_in_the_upstream_project() {
+    let transport = Rc::new(MemoryTransport::new());
+    transport.respond(
+        projects_url(),
+        HttpResponse::json(
+            200,
+            r#"[{"id":2,"name":"web-fork","forked_from_project":{"id":1}},{"id":1,"name":"web"}]"#,
+        ),
+    );
+    let shared = || commit_json("a1", "Alice", "alice@example.com", "feat: shared");
+    transport.respond(
+        commits_url(1),
+        HttpResponse::json(200, serde_json::json!([shared()]).to_string()),
+    );
+    transport.respond(
+        commits_url(2),
+        HttpResponse::json(
+            200,
+            serde_json::json!([shared(), commit_json("f1", "Bob", "bob@example.com", "feat: fork only")])
+                .to_string(),
+        ),
+    );
+    respond_commit(&transport, 1, "a1", serde_json::json!([added_lines("a.ts", 2)]));
+    respond_commit(&transport, 2, "a1", serde_json::json!([added_lines("a.ts", 2)]));
+    respond_commit(&transport, 2, "f1", serde_json::json!([added_lines("b.ts", 1)]));
+
+    let report = analyze(config(serde_json::json!({})), &transport, AnalysisOptions::default());
+    assert_eq!(report.duplicates_dropped, 1);
+    assert_eq!(total_row(&report, "alice@example.com").commits, 1);
+    let alice = report.commit_stats.iter().find(|c| c.email == "alice@example.com").unwrap();
+    assert_eq!(alice.project, "web");
+
+    let report = analyze(
+        config(serde_json::json!({ "dedup_policy": "count_everywhere" })),
+        &transport,
+        AnalysisOptions::default(),
+    );
+    assert_eq!(report.duplicates_dropped, 0);
+    assert_eq!(total_row(&report, "alice@example.com").commits, 2);
+}
+
+#[test]
+fn projects_are_processed_while_others_are_still_listing() {
+    let transport = Rc::new(MemoryTransport::new());
+    transport.respond(
+        projects_url(),
+        HttpResponse::json(200, r#"[{"id":1,"name":"web"},{"id":2,"name":"api"}]"#),
+    );
+    transport.respond(
+        commits_url(1),
+        HttpResponse::json(
+            200,
+            serde_json::json!([commit_json("a1", "Alice", "alice@example.com", "feat: login")]).to_string(),
+        ),
+    );
+    respond_commit(&transport, 1, "a1", serde_json::json!([added_lines("a.ts", 2)]));
+    transport.respond(commits_url(2), HttpResponse::json(200, "[]"));
+
+    // 第一个提交处理完后取消，此时第二个项目的提交记录还没有获取
+    let cancel = CancelToken::new();
+    let on_progress = {
+        let cancel = cancel.clone();
+        move |event: &ProgressEvent| {
+            if matches!(event, ProgressEvent::DiffsProcessed { .. }) {
+                cancel.cancel();
+            }
+        }
+    };
+    let report = analyze(
+        config(serde_json::json!({})),
+        &transport,
+        AnalysisOptions {
+            on_progress: Some(Box::new(on_progress)),
+            cancel: Some(cancel),
+            ..AnalysisOptions::default()
+        },
+    );
+
+    assert!(report.cancelled);
+    assert_eq!(total_row(&report, "alice@example.com").lines, 2);
+    assert!(!transport.requests().contains(&commits_url(2)));
+}
+
+#[test]
+fn excluded_merges_survive_resume() {
+    let transport = Rc::new(MemoryTransport::new());
+    transport.respond(projects_url(), HttpResponse::json(200, r#"[{"id":1,"name":"web"}]"#));
+    let mut merge = commit_json("m1", "Alice", "alice@example.com", "Merge branch 'feature'");
+    merge["parent_ids"] = serde_json::json!(["p1", "p2"]);
+    transport.respond(
+        commits_url(1),
+        HttpResponse::json(
+            200,
+            serde_json::json!([merge, commit_json("a1", "Alice", "alice@example.com", "feat: login")]).to_string(),
+        ),
+    );
+    respond_commit(&transport, 1, "a1", serde_json::json!([added_lines("a.ts", 2)]));
+
+    let saved: Rc<std::cell::RefCell<Option<Checkpoint>>> = Rc::default();
+    let options = AnalysisOptions {
+        on_checkpoint: Some(Box::new({
+            let saved = saved.clone();
+            move |checkpoint: &Checkpoint| *saved.borrow_mut() = Some(checkpoint.clone())
+        })),
+        ..AnalysisOptions::default()
+    };
+    let report = analyze(config(serde_json::json!({ "merge_policy": "exclude" })), &transport, options);
+    assert_eq!(report.merge_commits, 1);
+
+    // 从最终断点恢复，项目已完成，合并提交数来自断点
+    let checkpoint = saved.borrow_mut().take().expect("应当导出断点");
+    let resumed = analyze(
+        config(serde_json::json!({
+            "merge_policy": "exclude",
+            "resume_from": serde_json::to_value(&checkpoint).unwrap(),
+        })),
+        &transport,
+        AnalysisOptions::default(),
+    );
+    assert_eq!(resumed.merge_commits, 1);
+    assert_eq!(total_row(&resumed, "alice@example.com").commits, 1);
+}
+
+#[test]
+fn report_totals_include_bot_commits() {
+    let transport = Rc::new(MemoryTransport::new());
+    transport.respond(projects_url(), HttpResponse::json(200, r#"[{"id":1,"name":"web"}]"#));
+    let mut merge = commit_json("m1", "Administrator", "admin@example.com", "Merge branch 'feature'");
+    merge["parent_ids"] = serde_json::json!(["p1", "p2"]);
+    transport.respond(
+        commits_url(1),
+        HttpResponse::json(
+            200,
+            serde_json::json!([
+                merge,
+                commit_json("r1", "renovate[bot]", "renovate@example.com", "chore(deps): update"),
+                commit_json("a1", "Alice", "alice@example.com", "feat: login"),
+            ])
+            .to_string(),
+        ),
+    );
+    respond_commit(&transport, 1, "m1", serde_json::json!([]));
+    respond_commit(&transport, 1, "r1", serde_json::json!([added_lines("package-lock.json", 10)]));
+    respond_commit(&transport, 1, "a1", serde_json::json!([added_lines("a.ts", 2)]));
+
+    // 合并提交数不随 merge_policy、bot_policy 变化
+    for merge_policy in ["count_without_lines", "separate", "exclude"] {
+        for bot_policy in ["separate", "exclude"] {
+            let report = analyze(
+                config(serde_json::json!({ "merge_policy": merge_policy, "bot_policy": bot_policy })),
+                &transport,
+                AnalysisOptions::default(),
+            );
+            assert_eq!(report.merge_commits, 1, "{} {}", merge_policy, bot_policy);
+            let value = serde_json::to_value(&report).unwrap();
+            // bot_policy 为 exclude 时不下载机器人提交的差异
+            let lockfile = if bot_policy == "exclude" { serde_json::Value::Null } else { 10.into() };
+            assert_eq!(value["excludedLines"]["lockfile"], lockfile, "{} {}", merge_policy, bot_policy);
+        }
+    }
+}
+
+#[test]
+fn co_authors_do_not_inflate_project_totals() {
+    let transport = Rc::new(MemoryTransport::new());
+    transport.respond(projects_url(), HttpResponse::json(200, r#"[{"id":1,"name":"web"}]"#));
+    transport.respond(
+        commits_url(1),
+        HttpResponse::json(
+            200,
+            serde_json::json!([commit_json(
+                "a1",
+                "Alice",
+                "alice@example.com",
+                "feat: pair\n\nCo-authored-by: Bob <bob@example.com>"
+            )])
+            .to_string(),
+        ),
+    );
+    respond_commit(
+        &transport,
+        1,
+        "a1",
+        serde_json::json!([added_lines("a.ts", 4), added_lines("package-lock.json", 10)]),
+    );
+
+    for (policy, alice_lines, bob_lines) in [
+        ("primary_only", 4, None),
+        ("full_credit", 4, Some(4)),
+        ("even_split", 2, Some(2)),
+    ] {
+        let report = analyze(
+            config(serde_json::json!({ "coauthor_policy": policy })),
+            &transport,
+            AnalysisOptions::default(),
+        );
+        assert_eq!(total_row(&report, "alice@example.com").lines, alice_lines, "{}", policy);
+        let bob = report.code_stats.iter().find(|stat| stat.email == "bob@example.com");
+        assert_eq!(bob.map(|bob| bob.lines), bob_lines, "{}", policy);
+
+        // 提交和项目层面的数据只统计一次
+        let value = serde_json::to_value(&report).unwrap();
+        assert_eq!(report.commit_stats.len(), 1, "{}", policy);
+        assert_eq!(value["fileStats"]["byProject"][0]["lines"], 4, "{}", policy);
+        assert_eq!(value["commitTypes"]["byProject"][0]["commits"], 1, "{}", policy);
+        assert_eq!(value["excludedLines"]["lockfile"], 10, "{}", policy);
+    }
+}
+
+#[test]
+fn excluded_bot_authors_keep_human_co_authors() {
+    let transport = Rc::new(MemoryTransport::new());
+    transport.respond(projects_url(), HttpResponse::json(200, r#"[{"id":1,"name":"web"}]"#));
+    transport.respond(
+        commits_url(1),
+        HttpResponse::json(
+            200,
+            serde_json::json!([
+                commit_json(
+                    "a1",
+                    "Administrator",
+                    "admin@example.com",
+                    "chore: apply suggestion\n\nCo-authored-by: Alice <alice@example.com>"
+                ),
+                commit_json("r1", "renovate[bot]", "bot@renovateapp.com", "chore(deps): bump"),
+            ])
+            .to_string(),
+        ),
+    );
+    respond_commit(&transport, 1, "a1", serde_json::json!([added_lines("a.ts", 3)]));
+
+    let report = analyze(
+        config(serde_json::json!({ "bot_policy": "exclude" })),
+        &transport,
+        AnalysisOptions::default(),
+    );
+
+    let alice = total_row(&report, "alice@example.com");
+    assert_eq!((alice.commits, alice.lines), (1, 3));
+    assert_eq!(report.code_stats.iter().filter(|stat| stat.is_total == Some(true)).count(), 1);
+    assert!(report.bot_stats.is_none());
+    // 只有机器人作者的提交不请求差异
+    assert!(!transport.requests().iter().any(|url| url.contains("/commits/r1/")));
+}
+
+#[test]
+fn repository_files_are_only_read_for_projects_with_pending_commits() {
+    let transport = Rc::new(MemoryTransport::new());
+    transport.respond(
+        projects_url(),
+        HttpResponse::json(
+            200,
+            r#"[{"id":1,"name":"web","default_branch":"main"},{"id":2,"name":"idle","default_branch":"main"}]"#,
+        ),
+    );
+    transport.respond(
+        commits_url(1),
+        HttpResponse::json(
+            200,
+            serde_json::json!([commit_json("a1", "Alice", "alice@example.com", "feat: api client")]).to_string(),
+        ),
+    );
+    transport.respond(commits_url(2), HttpResponse::json(200, "[]"));
+    respond_commit(
+        &transport,
+        1,
+        "a1",
+        serde_json::json!([added_lines("src/client.ts", 2), added_lines("src/api.gen.ts", 30)]),
+    );
+    let file_url = |project_id: i64, path: &str| {
+        format!("{}/projects/{}/repository/files/{}/raw?ref=main", API, project_id, path)
+    };
+    transport.respond(
+        file_url(1, ".gitattributes"),
+        HttpResponse::json(200, "*.gen.ts linguist-generated\n"),
+    );
+
+    let report = analyze(
+        config(serde_json::json!({ "fetch_mailmap": true })),
+        &transport,
+        AnalysisOptions::default(),
+    );
+
+    assert_eq!(total_row(&report, "alice@example.com").lines, 2);
+    let value = serde_json::to_value(&report).unwrap();
+    assert_eq!(value["excludedLines"]["linguist_generated"], 30);
+    let requests = transport.requests();
+    assert!(requests.contains(&file_url(1, ".gitattributes")));
+    assert!(requests.contains(&file_url(1, ".mailmap")));
+    assert!(!requests.iter().any(|url| url.starts_with(&format!("{}/projects/2/repository/files/", API))));
+}
+
+// 每次请求都先让出一次执行权，模拟真实网络请求期间其他任务在许可队列中等待；sleep 永远不会结束
+struct YieldingTransport(MemoryTransport);
+
+impl Transport for YieldingTransport {
+    fn fetch<'a>(
+        &'a self,
+        request: &'a HttpRequest,
+    ) -> futures::future::LocalBoxFuture<'a, Result<HttpResponse, TransportError>> {
+        Box::pin(async move {
+            let mut yielded = false;
+            futures::future::poll_fn(|cx| {
+                if yielded {
+                    return std::task::Poll::Ready(());
+                }
+                yielded = true;
+                cx.waker().wake_by_ref();
+                std::task::Poll::Pending
+            })
+            .await;
+            self.0.fetch(request).await
+        })
+    }
+
+    fn sleep(&self, _ms: i32) -> futures::future::LocalBoxFuture<'static, ()> {
+        Box::pin(futures::future::pending())
+    }
+}
+
+#[test]
+fn queued_requests_respect_rate_limit_pause() {
+    let transport = MemoryTransport::new();
+    transport.respond(projects_url(), HttpResponse::json(200, r#"[{"id":1,"name":"web"}]"#));
+    let commits: Vec<_> = (0..10)
+        .map(|i| commit_json(&format!("c{}", i), "Alice", "alice@example.com", "feat: x"))
+        .collect();
+    transport.respond(commits_url(1), HttpResponse::json(200, serde_json::json!(commits).to_string()));
+    for i in 0..10 {
+        let base = format!("{}/projects/1/repository/commits/c{}", API, i);
+        for suffix in ["diff", "refs"] {
+            transport.respond(
+                format!("{}/{}", base, suffix),
+                HttpResponse::json(429, "").with_header("Retry-After", "3600"),
+            );
+        }
+    }
+    let transport = Rc::new(YieldingTransport(transport));
+
+    let mut pool = futures::executor::LocalPool::new();
+    let analysis = analyze_with_transport(
+        config(serde_json::json!({ "max_concurrent_requests": 1 })),
+        transport.clone(),
+        AnalysisOptions::default(),
+    );
+    futures::task::LocalSpawnExt::spawn_local(&pool.spawner(), async move {
+        let _ = analysis.await;
+    })
+    .unwrap();
+    // 限流暂停期间 sleep 不会结束，分析停在等待中
+    pool.run_until_stalled();
+
+    let sent = transport
+        .0
+        .requests()
+        .iter()
+        .filter(|url| url.contains("/repository/commits/c"))
+        .count();
+    assert_eq!(sent, 1);
+}
+
+#[test]
+fn fast_mode_uses_commit_list_stats_without_filters() {
+    let transport = Rc::new(MemoryTransport::new());
+    transport.respond(projects_url(), HttpResponse::json(200, r#"[{"id":1,"name":"web"}]"#));
+    let mut commit = commit_json("a1", "Alice", "alice@example.com", "feat: login");
+    commit["stats"] = serde_json::json!({ "additions": 7, "deletions": 2 });
+    let body = serde_json::json!([commit]).to_string();
+    let stats_url = format!("{}&with_stats=true", commits_url(1));
+    transport.respond(stats_url.clone(), HttpResponse::json(200, body.clone()));
+    transport.respond(commits_url(1), HttpResponse::json(200, body));
+    respond_commit(
+        &transport,
+        1,
+        "a1",
+        serde_json::json!([added_lines("src/a.ts", 3), added_lines("README.md", 4)]),
+    );
+
+    let report = analyze(config(serde_json::json!({ "fast_mode": true })), &transport, AnalysisOptions::default());
+    let alice = total_row(&report, "alice@example.com");
+    assert_eq!((alice.additions, alice.deletions, alice.lines), (7, 2, 9));
+    assert_eq!((alice.files, alice.size), (0, 0));
+    let requests = transport.requests();
+    assert!(requests.contains(&stats_url));
+    assert!(!requests.iter().any(|url| url.ends_with("/diff")));
+
+    // 配置了过滤条件时仍然逐个下载差异
+    let before = transport.requests().len();
+    let report = analyze(
+        config(serde_json::json!({ "fast_mode": true, "valid_extensions": [".ts"] })),
+        &transport,
+        AnalysisOptions::default(),
+    );
+    assert_eq!(total_row(&report, "alice@example.com").lines, 3);
+    let requests = &transport.requests()[before..];
+    assert!(requests.contains(&commits_url(1)));
+    assert!(!requests.contains(&stats_url));
+    assert!(requests.iter().any(|url| url.ends_with("/commits/a1/diff")));
+}
+
+#[test]
+fn resolves_authors_to_gitlab_users() {
+    let transport = Rc::new(MemoryTransport::new());
+    transport.respond(projects_url(), HttpResponse::json(200, r#"[{"id":1,"name":"web"}]"#));
+    transport.respond(
+        commits_url(1),
+        HttpResponse::json(
+            200,
+            serde_json::json!([
+                commit_json("a1", "Alice", "alice@example.com", "feat: a"),
+                commit_json("b1", "Bob", "bob@home.net", "feat: b"),
+                commit_json("c1", "Carol", "carol@home.net", "feat: c"),
+                commit_json("d1", "Wei Wang", "wei@home.net", "feat: d"),
+            ])
+            .to_string(),
+        ),
+    );
+    for sha in ["a1", "b1", "c1", "d1"] {
+        respond_commit(&transport, 1, sha, serde_json::json!([added_lines("a.ts", 1)]));
+    }
+    let user = |id: i64, username: &str, name: &str, public_email: Option<&str>| {
+        serde_json::json!({
+            "id": id, "username": username, "name": name, "state": "active",
+            "avatar_url": null, "web_url": null, "public_email": public_email,
+        })
+    };
+    let search_url = |email: &str| format!("{}/users?search={}", API, email.replace('@', "%40"));
+    // 邮箱搜索有多个结果时按公开邮箱确定
+    transport.respond(
+        search_url("alice@example.com"),
+        HttpResponse::json(
+            200,
+            serde_json::json!([
+                user(1, "alice", "Alice", Some("alice@example.com")),
+                user(9, "alice2", "Alice Two", None),
+            ])
+            .to_string(),
+        ),
+    );
+    for email in ["bob@home.net", "carol@home.net", "wei@home.net"] {
+        transport.respond(search_url(email), HttpResponse::json(200, "[]"));
+    }
+    // 搜索不到时按群组成员的用户名匹配，同名成员不唯一时视为未匹配
+    transport.respond(
+        format!("{}/groups/1/members/all?per_page=100", API),
+        HttpResponse::json(
+            200,
+            serde_json::json!([
+                user(2, "bob", "Robert", None),
+                user(3, "wang", "Wei Wang", None),
+                user(4, "wwang", "Wei Wang", None),
+            ])
+            .to_string(),
+        ),
+    );
+
+    let report = analyze(config(serde_json::json!({ "resolve_users": true })), &transport, AnalysisOptions::default());
+
+    let user_id = |email: &str| total_row(&report, email).gitlab_user.as_ref().map(|user| user.id);
+    assert_eq!(user_id("alice@example.com"), Some(1));
+    assert_eq!(user_id("bob@home.net"), Some(2));
+    assert_eq!(user_id("carol@home.net"), None);
+    assert_eq!(user_id("wei@home.net"), None);
+
+    let mut unmatched: Vec<_> = report
+        .unmatched_authors
+        .as_ref()
+        .expect("应当返回未匹配的作者")
+        .iter()
+        .map(|author| author.email.as_str())
+        .collect();
+    unmatched.sort();
+    assert_eq!(unmatched, vec!["carol@home.net", "wei@home.net"]);
+}
+
+#[test]
+fn revert_policies() {
+    let transport = Rc::new(MemoryTransport::new());
+    transport.respond(projects_url(), HttpResponse::json(200, r#"[{"id":1,"name":"web"}]"#));
+    transport.respond(
+        commits_url(1),
+        HttpResponse::json(
+            200,
+            serde_json::json!([
+                commit_json("aaaaaaa1", "Alice", "alice@example.com", "feat: a"),
+                commit_json("bbbbbbb1", "Bob", "bob@example.com", "Revert \"feat: a\"\n\nThis reverts commit aaaaaaa."),
+                commit_json("ccccccc1", "Carol", "carol@example.com", "feat: c"),
+                commit_json("ddddddd1", "Dave", "dave@example.com", "Revert \"feat: c\"\n\nThis reverts merge request !7"),
+                commit_json("eeeeeee1", "Eve", "eve@example.com", "feat: unrelated"),
+            ])
+            .to_string(),
+        ),
+    );
+    let deleted_lines = |path: &str, n: usize| {
+        let body: String = (0..n).map(|i| format!("-line {}\n", i)).collect();
+        serde_json::json!({ "new_path": path, "diff": format!("@@ -1,{} +0,0 @@\n{}", n, body) })
+    };
+    respond_commit(&transport, 1, "aaaaaaa1", serde_json::json!([added_lines("a.ts", 3)]));
+    respond_commit(&transport, 1, "bbbbbbb1", serde_json::json!([deleted_lines("a.ts", 3)]));
+    respond_commit(&transport, 1, "ccccccc1", serde_json::json!([added_lines("c.ts", 2)]));
+    respond_commit(&transport, 1, "ddddddd1", serde_json::json!([deleted_lines("c.ts", 2)]));
+    respond_commit(&transport, 1, "eeeeeee1", serde_json::json!([added_lines("e.ts", 5)]));
+    let merge_request = format!("{}/projects/1/merge_requests/7", API);
+    transport.respond(
+        merge_request.clone(),
+        HttpResponse::json(200, r#"{"merge_commit_sha":null,"squash_commit_sha":null}"#),
+    );
+    transport.respond(
+        format!("{}/commits?per_page=100", merge_request),
+        HttpResponse::json(200, r#"[{"id":"ccccccc1"}]"#),
+    );
+
+    // 每个作者的 (commits, lines, revertedLines)，None 表示没有统计
+    type Row = Option<(u32, u32, u32)>;
+    let expected: [(&str, [Row; 5]); 3] = [
+        ("net", [Some((1, 0, 3)), Some((1, 0, 0)), Some((1, 0, 2)), Some((1, 0, 0)), Some((1, 5, 0))]),
+        ("exclude", [Some((0, 0, 3)), None, Some((0, 0, 2)), None, Some((1, 5, 0))]),
+        ("count", [Some((1, 3, 3)), Some((1, 3, 0)), Some((1, 2, 2)), Some((1, 2, 0)), Some((1, 5, 0))]),
+    ];
+    let emails = ["alice", "bob", "carol", "dave", "eve"].map(|name| format!("{}@example.com", name));
+    for (policy, rows) in expected {
+        let report = analyze(
+            config(serde_json::json!({ "revert_policy": policy })),
+            &transport,
+            AnalysisOptions::default(),
+        );
+        for (email, expected) in emails.iter().zip(rows) {
+            let actual = report
+                .code_stats
+                .iter()
+                .find(|stat| &stat.email == email)
+                .map(|stat| (stat.commits, stat.lines, stat.reverted_lines));
+            assert_eq!(actual, expected, "{} {}", policy, email);
+        }
+    }
+}
@@ -0,0 +1,357 @@
+// HTTP 传输层
+// 分析流程只通过 Transport 发起请求，不直接依赖 window，从而可以运行在浏览器主线程、Web Worker、Node 以及原生测试环境中
+// browser feature：主线程使用 window.fetch，Worker 中使用 self.fetch
+// node feature：使用 Node 18+ 内置的全局 fetch
+
+use crate::cancel::CancelToken;
+use futures::future::LocalBoxFuture;
+use js_sys::{Array, Function, Object, Promise, Reflect};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{AbortController, Headers, Request, RequestInit, RequestMode, Response, WorkerGlobalScope};
+
+#[cfg(not(any(feature = "browser", feature = "node")))]
+compile_error!("需要至少启用 browser 或 node 其中一个 feature");
+
+// === 请求与响应类型 ===
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    // 超时时间（毫秒）
+    pub timeout: i32,
+    // 取消后中断请求
+    pub cancel: CancelToken,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub status_text: String,
+    // header 名称统一为小写
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl HttpResponse {
+    // 构造一个 json 响应，主要用于内存传输层
+    pub fn json(status: u16, body: impl Into<String>) -> Self {
+        let mut headers = HashMap::new();
+        headers.insert("content-type".to_string(), "application/json".to_string());
+        HttpResponse {
+            status,
+            status_text: String::new(),
+            headers,
+            body: body.into(),
+        }
+    }
+
+    pub fn ok(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_ascii_lowercase()).map(String::as_str)
+    }
+
+    pub fn with_header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.insert(name.to_ascii_lowercase(), value.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum TransportError {
+    Timeout,
+    Network(String),
+    // 请求被取消
+    Aborted,
+}
+
+// === 传输层抽象 ===
+pub trait Transport {
+    // 发起 GET 请求，超过 request.timeout 后返回 TransportError::Timeout
+    fn fetch<'a>(&'a self, request: &'a HttpRequest) -> LocalBoxFuture<'a, Result<HttpResponse, TransportError>>;
+
+    // 等待指定毫秒数，用于重试间隔
+    fn sleep(&self, ms: i32) -> LocalBoxFuture<'static, ()>;
+}
+
+// 根据启用的 feature 选择默认的传输层
+#[cfg(feature = "browser")]
+pub fn default_transport() -> Rc<dyn Transport> {
+    if web_sys::window().is_some() {
+        Rc::new(BrowserTransport)
+    } else {
+        Rc::new(WorkerTransport)
+    }
+}
+
+#[cfg(all(feature = "node", not(feature = "browser")))]
+pub fn default_transport() -> Rc<dyn Transport> {
+    Rc::new(NodeTransport)
+}
+
+// === 基于 js fetch 的实现 ===
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = fetch)]
+    fn global_fetch(input: &Request) -> Promise;
+
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn global_set_timeout(handler: &Function, timeout: i32, argument: &JsValue) -> JsValue;
+}
+
+// 浏览器主线程，使用 window.fetch
+pub struct BrowserTransport;
+
+// Web Worker，使用 self.fetch
+pub struct WorkerTransport;
+
+// Node 18+ / Deno，使用全局 fetch
+pub struct NodeTransport;
+
+impl Transport for BrowserTransport {
+    fn fetch<'a>(&'a self, request: &'a HttpRequest) -> LocalBoxFuture<'a, Result<HttpResponse, TransportError>> {
+        Box::pin(js_fetch(
+            request,
+            |req| web_sys::window().unwrap().fetch_with_request(req),
+            |handler, timeout, argument| {
+                web_sys::window()
+                    .unwrap()
+                    .set_timeout_with_callback_and_timeout_and_arguments_1(handler, timeout, argument)
+                    .unwrap();
+            },
+        ))
+    }
+
+    fn sleep(&self, ms: i32) -> LocalBoxFuture<'static, ()> {
+        Box::pin(js_sleep(ms, |handler, timeout, argument| {
+            web_sys::window()
+                .unwrap()
+                .set_timeout_with_callback_and_timeout_and_arguments_1(handler, timeout, argument)
+                .unwrap();
+        }))
+    }
+}
+
+impl Transport for WorkerTransport {
+    fn fetch<'a>(&'a self, request: &'a HttpRequest) -> LocalBoxFuture<'a, Result<HttpResponse, TransportError>> {
+        Box::pin(js_fetch(
+            request,
+            |req| worker_scope().fetch_with_request(req),
+            |handler, timeout, argument| {
+                worker_scope()
+                    .set_timeout_with_callback_and_timeout_and_arguments_1(handler, timeout, argument)
+                    .unwrap();
+            },
+        ))
+    }
+
+    fn sleep(&self, ms: i32) -> LocalBoxFuture<'static, ()> {
+        Box::pin(js_sleep(ms, |handler, timeout, argument| {
+            worker_scope()
+                .set_timeout_with_callback_and_timeout_and_arguments_1(handler, timeout, argument)
+                .unwrap();
+        }))
+    }
+}
+
+impl Transport for NodeTransport {
+    fn fetch<'a>(&'a self, request: &'a HttpRequest) -> LocalBoxFuture<'a, Result<HttpResponse, TransportError>> {
+        Box::pin(js_fetch(request, global_fetch, |handler, timeout, argument| {
+            global_set_timeout(handler, timeout, argument);
+        }))
+    }
+
+    fn sleep(&self, ms: i32) -> LocalBoxFuture<'static, ()> {
+        Box::pin(js_sleep(ms, |handler, timeout, argument| {
+            global_set_timeout(handler, timeout, argument);
+        }))
+    }
+}
+
+fn worker_scope() -> WorkerGlobalScope {
+    js_sys::global().unchecked_into::<WorkerGlobalScope>()
+}
+
+// 通用的请求构建函数
+fn build_request(request: &HttpRequest) -> Result<(Request, AbortController), JsValue> {
+    let opts = RequestInit::new();
+    opts.set_method("GET");
+    opts.set_mode(RequestMode::Cors);
+
+    let headers = Headers::new()?;
+    for (name, value) in &request.headers {
+        headers.append(name, value)?;
+    }
+    opts.set_headers(&headers);
+
+    let abort_controller = AbortController::new()?;
+    opts.set_signal(Some(&abort_controller.signal()));
+
+    let js_request = Request::new_with_str_and_init(&request.url, &opts)?;
+
+    Ok((js_request, abort_controller))
+}
+
+// 使用 js fetch 发起请求，并与超时 Promise 竞速
+async fn js_fetch(
+    request: &HttpRequest,
+    fetch: impl Fn(&Request) -> Promise,
+    set_timeout: impl Fn(&Function, i32, &JsValue),
+) -> Result<HttpResponse, TransportError> {
+    if request.cancel.is_cancelled() {
+        return Err(TransportError::Aborted);
+    }
+
+    let (js_request, abort_controller) =
+        build_request(request).map_err(|e| TransportError::Network(format!("{:?}", e)))?;
+
+    // 外部 signal 触发时中断本次请求
+    let abort_listener = request.cancel.signal().map(|signal| {
+        let controller = abort_controller.clone();
+        let listener = Closure::<dyn FnMut()>::new(move || controller.abort());
+        let _ = signal.add_event_listener_with_callback("abort", listener.as_ref().unchecked_ref());
+        (signal, listener)
+    });
+
+    let result = js_fetch_with_timeout(request, &js_request, &abort_controller, fetch, set_timeout).await;
+
+    if let Some((signal, listener)) = abort_listener {
+        let _ = signal.remove_event_listener_with_callback("abort", listener.as_ref().unchecked_ref());
+    }
+
+    match result {
+        Err(_) if request.cancel.is_cancelled() => Err(TransportError::Aborted),
+        result => result,
+    }
+}
+
+async fn js_fetch_with_timeout(
+    request: &HttpRequest,
+    js_request: &Request,
+    abort_controller: &AbortController,
+    fetch: impl Fn(&Request) -> Promise,
+    set_timeout: impl Fn(&Function, i32, &JsValue),
+) -> Result<HttpResponse, TransportError> {
+    // 创建超时 Promise
+    let timeout_value = Object::new();
+    Reflect::set(&timeout_value, &"timeout".into(), &JsValue::TRUE).unwrap();
+    let timeout_promise = Promise::new(&mut |resolve, _reject| {
+        set_timeout(&resolve, request.timeout, &timeout_value);
+    });
+
+    let fetch_promise = fetch(js_request);
+    let race_promise = Promise::race(&Array::of2(&fetch_promise, &timeout_promise));
+
+    match JsFuture::from(race_promise).await {
+        Ok(value) => {
+            if is_timeout(&value) {
+                abort_controller.abort();
+                return Err(TransportError::Timeout);
+            }
+            read_response(value).await.map_err(|e| {
+                abort_controller.abort();
+                TransportError::Network(format!("{:?}", e))
+            })
+        }
+        Err(e) => {
+            abort_controller.abort();
+            Err(TransportError::Network(format!("{:?}", e)))
+        }
+    }
+}
+
+// 辅助函数：检查是否超时
+fn is_timeout(value: &JsValue) -> bool {
+    value.is_object()
+        && !value.is_instance_of::<Response>()
+        && Reflect::get(value, &"timeout".into())
+            .map(|v| v.is_truthy())
+            .unwrap_or(false)
+}
+
+// 读取状态码、响应头和响应体
+async fn read_response(value: JsValue) -> Result<HttpResponse, JsValue> {
+    let response: Response = value.dyn_into()?;
+
+    let mut headers = HashMap::new();
+    if let Some(entries) = js_sys::try_iter(&response.headers())? {
+        for entry in entries {
+            let entry: Array = entry?.dyn_into()?;
+            if let (Some(name), Some(value)) = (entry.get(0).as_string(), entry.get(1).as_string()) {
+                headers.insert(name.to_ascii_lowercase(), value);
+            }
+        }
+    }
+
+    let body = JsFuture::from(response.text()?).await?.as_string().unwrap_or_default();
+
+    Ok(HttpResponse {
+        status: response.status(),
+        status_text: response.status_text(),
+        headers,
+        body,
+    })
+}
+
+// 添加一个辅助函数来创建延迟
+async fn js_sleep(ms: i32, set_timeout: impl Fn(&Function, i32, &JsValue)) {
+    let promise = Promise::new(&mut |resolve, _| {
+        set_timeout(&resolve, ms, &JsValue::UNDEFINED);
+    });
+    let _ = JsFuture::from(promise).await;
+}
+
+// === 内存实现 ===
+// 按 URL 返回预设的响应，不发起真实请求，也不会真正等待，用于测试或离线回放
+// 未预设的 URL 返回 404
+#[derive(Default)]
+pub struct MemoryTransport {
+    responses: RefCell<HashMap<String, HttpResponse>>,
+    requests: RefCell<Vec<String>>,
+}
+
+impl MemoryTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 预设某个 URL 的响应
+    pub fn respond(&self, url: impl Into<String>, response: HttpResponse) {
+        self.responses.borrow_mut().insert(url.into(), response);
+    }
+
+    // 按顺序返回已经发起过的请求 URL
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.borrow().clone()
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn fetch<'a>(&'a self, request: &'a HttpRequest) -> LocalBoxFuture<'a, Result<HttpResponse, TransportError>> {
+        if request.cancel.is_cancelled() {
+            return Box::pin(futures::future::ready(Err(TransportError::Aborted)));
+        }
+        self.requests.borrow_mut().push(request.url.clone());
+        let response = self
+            .responses
+            .borrow()
+            .get(&request.url)
+            .cloned()
+            .unwrap_or_else(|| HttpResponse {
+                status: 404,
+                status_text: "Not Found".to_string(),
+                ..Default::default()
+            });
+        Box::pin(futures::future::ready(Ok(response)))
+    }
+
+    fn sleep(&self, _ms: i32) -> LocalBoxFuture<'static, ()> {
+        Box::pin(futures::future::ready(()))
+    }
+}
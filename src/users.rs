@@ -0,0 +1,219 @@
+// 把提交作者匹配到 GitLab 账号
+// 提交中只有自由填写的作者名和邮箱，这里先按邮箱搜索用户（GitLab 对邮箱只做精确匹配，非管理员只能匹配公开邮箱），
+// 搜索不到时再用群组成员按用户名（邮箱 @ 前的部分）或名字匹配，结果不唯一时视为未匹配
+
+use crate::{fetch_json, fetch_paginated, pagination, AnalysisContext, AnalysisError, RequestContext, RequestKind};
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// GitLab 账号信息
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GitlabUser {
+    pub id: i64,
+    pub username: String,
+    pub name: String,
+    // active、blocked、deactivated 等
+    pub state: String,
+    pub avatar_url: Option<String>,
+    pub web_url: Option<String>,
+    // 是否为机器人账号（项目/群组访问令牌、服务账号等）
+    pub bot: bool,
+}
+
+// 用户接口和成员接口返回的字段
+#[derive(Deserialize, Debug, Clone)]
+struct ApiUser {
+    id: i64,
+    username: String,
+    name: String,
+    #[serde(default)]
+    state: String,
+    avatar_url: Option<String>,
+    web_url: Option<String>,
+    #[serde(default)]
+    public_email: Option<String>,
+    #[serde(default)]
+    bot: bool,
+}
+
+impl From<&ApiUser> for GitlabUser {
+    fn from(user: &ApiUser) -> Self {
+        GitlabUser {
+            id: user.id,
+            username: user.username.clone(),
+            name: user.name.clone(),
+            state: user.state.clone(),
+            avatar_url: user.avatar_url.clone(),
+            web_url: user.web_url.clone(),
+            bot: user.bot,
+        }
+    }
+}
+
+// 需要匹配的作者
+pub struct AuthorIdentity {
+    pub key: String,
+    pub name: String,
+    pub email: String,
+}
+
+// 匹配作者对应的 GitLab 账号，返回作者 key 到账号的映射，未匹配的作者不在结果中
+pub(crate) async fn resolve_users(
+    ctx: &AnalysisContext,
+    authors: &[AuthorIdentity],
+) -> HashMap<String, GitlabUser> {
+    let members = match get_group_members(ctx).await {
+        Ok(members) => members,
+        Err(e) => {
+            crate::log_error(&format!("[获取群组成员失败] {}", e));
+            Vec::new()
+        }
+    };
+
+    let futures: Vec<_> = authors
+        .iter()
+        .map(|author| async {
+            let user = match search_user(ctx, &author.email).await {
+                Ok(Some(user)) => Some(user),
+                _ => match_member(&members, author),
+            };
+            user.map(|user| (author.key.clone(), user))
+        })
+        .collect();
+
+    join_all(futures).await.into_iter().flatten().collect()
+}
+
+// 群组（包括继承自上级群组）的所有成员
+async fn get_group_members(ctx: &AnalysisContext) -> Result<Vec<ApiUser>, AnalysisError> {
+    let url = format!(
+        "{}/groups/{}/members/all?per_page={}",
+        ctx.config.gitlab_api,
+        ctx.config.group_id,
+        pagination::MAX_PER_PAGE
+    );
+    let context = RequestContext {
+        kind: RequestKind::Users,
+        operation: "获取群组成员".to_string(),
+        project_name: None,
+        author: None,
+    };
+
+    let mut members: Vec<ApiUser> =
+        fetch_paginated(ctx, url, pagination::MAX_PER_PAGE, &context, None, |_, _| {})
+            .await?
+            .items;
+    // 同一用户可能同时是多个上级群组的成员
+    members.sort_by_key(|m| m.id);
+    members.dedup_by_key(|m| m.id);
+    Ok(members)
+}
+
+// 按邮箱搜索用户，只有一个结果或公开邮箱一致时视为匹配
+async fn search_user(ctx: &AnalysisContext, email: &str) -> Result<Option<GitlabUser>, AnalysisError> {
+    if email.is_empty() {
+        return Ok(None);
+    }
+    let url = format!(
+        "{}/users?search={}",
+        ctx.config.gitlab_api,
+        crate::encode_uri_component(email)
+    );
+    let context = RequestContext {
+        kind: RequestKind::Users,
+        operation: "按邮箱搜索用户".to_string(),
+        project_name: None,
+        author: Some(email.to_string()),
+    };
+
+    let users: Vec<ApiUser> = fetch_json(ctx, &url, &context).await?;
+    let user = match users.as_slice() {
+        [user] => Some(user),
+        users => users.iter().find(|user| {
+            user.public_email
+                .as_deref()
+                .is_some_and(|public_email| public_email.eq_ignore_ascii_case(email))
+        }),
+    };
+    Ok(user.map(GitlabUser::from))
+}
+
+// 在群组成员中按用户名或名字匹配
+fn match_member(members: &[ApiUser], author: &AuthorIdentity) -> Option<GitlabUser> {
+    let local_part = author.email.split('@').next().unwrap_or("");
+    let by_username: Vec<_> = members
+        .iter()
+        .filter(|m| !local_part.is_empty() && m.username.eq_ignore_ascii_case(local_part))
+        .collect();
+    if let [member] = by_username.as_slice() {
+        return Some(GitlabUser::from(*member));
+    }
+
+    let by_name: Vec<_> = members
+        .iter()
+        .filter(|m| m.name.eq_ignore_ascii_case(&author.name) || m.username.eq_ignore_ascii_case(&author.name))
+        .collect();
+    match by_name.as_slice() {
+        [member] => Some(GitlabUser::from(*member)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(id: i64, username: &str, name: &str) -> ApiUser {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "username": username,
+            "name": name,
+            "state": "active",
+            "avatar_url": null,
+            "web_url": null,
+        }))
+        .unwrap()
+    }
+
+    fn author(name: &str, email: &str) -> AuthorIdentity {
+        AuthorIdentity {
+            key: email.to_lowercase(),
+            name: name.to_string(),
+            email: email.to_string(),
+        }
+    }
+
+    fn matched(members: &[ApiUser], name: &str, email: &str) -> Option<i64> {
+        match_member(members, &author(name, email)).map(|user| user.id)
+    }
+
+    #[test]
+    fn matches_username_from_email_local_part() {
+        let members = [member(1, "alice", "Alice Liu"), member(2, "bob", "Bob")];
+        assert_eq!(matched(&members, "A. Liu", "Alice@home.net"), Some(1));
+    }
+
+    #[test]
+    fn falls_back_to_name_or_username() {
+        let members = [member(1, "aliu", "Alice Liu"), member(2, "bob", "Bob")];
+        assert_eq!(matched(&members, "alice liu", "alice@home.net"), Some(1));
+        assert_eq!(matched(&members, "BOB", "robert@home.net"), Some(2));
+        assert_eq!(matched(&members, "Carol", "carol@home.net"), None);
+    }
+
+    #[test]
+    fn ambiguous_matches_are_unmatched() {
+        let members = [member(1, "wang", "Wei Wang"), member(2, "wwang", "Wei Wang")];
+        assert_eq!(matched(&members, "Wei Wang", "wei@home.net"), None);
+        // 用户名唯一匹配时不受同名成员影响
+        assert_eq!(matched(&members, "Wei Wang", "wwang@corp.com"), Some(2));
+    }
+
+    #[test]
+    fn empty_email_does_not_match_empty_username() {
+        let members = [member(1, "", "Someone"), member(2, "dev", "Dev")];
+        assert_eq!(matched(&members, "Dev", ""), Some(2));
+    }
+}